@@ -46,7 +46,7 @@ async fn main() {
 
     app.get("/auth", auth);
 
-    app.listen(8080, || {}).await;
+    app.listen(8080, |_| {}).await.unwrap();
 }
 
 async fn cookie_test(req: HttpRequest, res: HttpResponse) -> HttpResponse {
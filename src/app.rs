@@ -1,7 +1,35 @@
-use crate::request::HttpRequest;
+use crate::body_stream::ForwardingBody;
+use crate::request::{to_http_method, HttpRequest, DEFAULT_BODY_LIMIT};
 use crate::response::HttpResponse;
-use crate::types::{Fut, Handler, HttpMethods, Middleware, Next, Routes};
-use std::{collections::HashMap, future::Future, sync::Arc};
+use crate::router::{RouteMatch, Router};
+use crate::types::{
+    Endpoint, ErrorMiddleware, ErrorNext, Fut, Handler, HookError, HttpMethods, LifecycleHook,
+    Middleware, MiddlewarePath, MultipartConfig, Next, RequestBodyType, ResponseHook, RipressError,
+    RouterError, Routes, StateMap, WsHandler,
+};
+use crate::ws::{self, WebSocket};
+use actix_web::{body::BoxBody, http::header};
+use futures_util::future::{select, Either, LocalBoxFuture};
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    future::Future,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+fn wrap_lifecycle_hook<F, Fut, E>(hook: F) -> LifecycleHook
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Into<HookError>,
+{
+    Arc::new(move || {
+        let fut = hook();
+        Box::pin(async move { fut.await.map_err(Into::into) })
+    })
+}
 
 pub(crate) fn box_future<F>(future: F) -> Fut
 where
@@ -10,9 +38,342 @@ where
     Box::pin(future)
 }
 
+/// The latest request passed down the middleware chain, for the error middlewares and response hooks.
+type SeenRequest = Arc<Mutex<Option<HttpRequest>>>;
+
+/// Records the request it is called with before running the wrapped middleware.
+struct RecordRequest {
+    middleware: Box<dyn Middleware>,
+    seen: SeenRequest,
+}
+
+impl Middleware for RecordRequest {
+    fn handle(&self, req: HttpRequest, res: HttpResponse, next: Next) -> Fut {
+        *self.seen.lock().unwrap() = Some(req.clone());
+        self.middleware.handle(req, res, next)
+    }
+
+    fn clone_box(&self) -> Box<dyn Middleware> {
+        Box::new(RecordRequest {
+            middleware: self.middleware.clone(),
+            seen: self.seen.clone(),
+        })
+    }
+}
+
+/// The maximum request body sizes of an `App`.
+#[derive(Clone)]
+pub(crate) struct BodyLimits {
+    default: usize,
+    by_content_type: Vec<(RequestBodyType, usize)>,
+}
+
+impl BodyLimits {
+    /// Returns the limit for bodies of the content type.
+    pub(crate) fn limit_for(&self, content_type: RequestBodyType) -> usize {
+        self.by_content_type
+            .iter()
+            .find(|(body_type, _)| *body_type == content_type)
+            .map_or(self.default, |(_, limit)| *limit)
+    }
+}
+
+/// A route added to an `App`, returned by the route methods to configure it further.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, context::{HttpRequest, HttpResponse}};
+///
+/// async fn upload(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+///     res.ok().text("Uploaded")
+/// }
+///
+/// let mut app = App::new();
+/// app.post("/upload", upload).body_limit(10 * 1024 * 1024);
+/// ```
+pub struct Route<'a> {
+    app: &'a mut App,
+    path: &'static str,
+    methods: Vec<HttpMethods>,
+}
+
+impl Route<'_> {
+    /// Sets the maximum request body size of the route, in bytes.
+    ///
+    /// Overrides the limits set with `App::body_limit` and `App::body_limit_for`.
+    /// Larger bodies are answered with 413 Payload Too Large.
+    ///
+    /// ## Arguments
+    ///
+    /// * `limit` - The maximum body size in bytes.
+    pub fn body_limit(mut self, limit: usize) -> Self {
+        self.update(|endpoint| endpoint.body_limit = Some(limit));
+        self
+    }
+
+    /// Streams the request body of the route instead of reading it before the handler runs.
+    ///
+    /// The handler reads the body with `req.body_stream()` or a `BodyStream`
+    /// argument, the body limit is enforced while it streams.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::{app::App, context::HttpResponse, types::{handler, BodyStream}};
+    ///
+    /// async fn proxy(body: BodyStream, res: HttpResponse) -> HttpResponse {
+    ///     res.ok()
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.post("/proxy", handler(proxy)).stream_body();
+    /// ```
+    pub fn stream_body(mut self) -> Self {
+        self.update(|endpoint| endpoint.stream_body = true);
+        self
+    }
+
+    fn update(&mut self, update: impl Fn(&mut Endpoint)) {
+        if let Some(endpoints) = self.app.routes.get_mut(self.path) {
+            for method in &self.methods {
+                if let Some(endpoint) = endpoints.get_mut(method) {
+                    update(endpoint);
+                }
+            }
+        }
+    }
+}
+
+/// The routes and middlewares of an `App`, compiled once when the server starts
+/// and shared by every worker.
+pub(crate) struct DispatchTable {
+    router: Router<Endpoint>,
+    middlewares: Vec<Box<dyn Middleware>>,
+    error_middlewares: Vec<Box<dyn ErrorMiddleware>>,
+    response_hooks: Vec<ResponseHook>,
+    state: Arc<StateMap>,
+    body_limits: BodyLimits,
+    multipart: MultipartConfig,
+}
+
+impl DispatchTable {
+    /// Looks up the handler registered for the method and path.
+    pub(crate) fn lookup(&self, method: &HttpMethods, path: &str) -> RouteMatch<'_, Endpoint> {
+        self.router.lookup(method, path)
+    }
+
+    /// Runs an incoming actix request through the middlewares and the matching handler.
+    pub(crate) async fn dispatch(
+        &self,
+        req: actix_web::HttpRequest,
+        payload: actix_web::web::Payload,
+    ) -> actix_web::HttpResponse {
+        let method = match to_http_method(req.method()) {
+            Some(method) => method,
+            None => return self.unmatched(&req, HttpResponse::new().not_found().text("")),
+        };
+
+        let (endpoint, params, pattern) = match self.lookup(&method, req.path()) {
+            RouteMatch::Found {
+                value,
+                params,
+                pattern,
+            } => (value, params, pattern),
+            RouteMatch::MethodNotAllowed(allowed) => {
+                let allowed: Vec<String> = allowed.iter().map(|m| m.to_string()).collect();
+                let response = HttpResponse::new()
+                    .status(405)
+                    .set_header("Allow", allowed.join(", "))
+                    .text("");
+                return self.unmatched(&req, response);
+            }
+            RouteMatch::NotFound => {
+                return self.unmatched(&req, HttpResponse::new().not_found().text(""))
+            }
+        };
+
+        let mut our_req = HttpRequest::from_actix_parts(&req);
+        our_req.set_route(pattern, params);
+        our_req.set_state(self.state.clone());
+        // Requests that aren't handshakes run the route's handler, answering 426
+        let ws = endpoint.ws.as_ref().filter(|_| ws::is_handshake(&req));
+        let body_limit = endpoint
+            .body_limit
+            .unwrap_or_else(|| self.body_limits.limit_for(our_req.body_type()));
+        // WebSocket routes keep the connection for the socket, their body isn't read
+        let (body, ws_payload): (Result<Option<LocalBoxFuture<'static, ()>>, RipressError>, _) =
+            if ws.is_some() {
+                (Ok(None), Some(payload))
+            } else if endpoint.stream_body {
+                let body = match our_req.stream_body(payload, body_limit, &self.multipart) {
+                    Ok(forward) => Ok(Some(Box::pin(forward) as LocalBoxFuture<'static, ()>)),
+                    Err(err) => Err(err),
+                };
+                (body, None)
+            } else {
+                let body = our_req
+                    .read_body(payload, body_limit, &self.multipart)
+                    .await
+                    .map(|_| None);
+                (body, None)
+            };
+        // The upgrade happens once the middlewares let the request reach the route
+        let upgrade_req = Arc::new(Mutex::new(None));
+        let handler = match ws {
+            Some(_) => {
+                let upgrade_req = upgrade_req.clone();
+                Arc::new(move |req, res: HttpResponse| {
+                    *upgrade_req.lock().unwrap() = Some(req);
+                    // The status is set to 101 once the upgrade succeeded
+                    box_future(async move { res })
+                }) as Handler
+            }
+            None => endpoint.handler.clone(),
+        };
+        let our_res = HttpResponse::new();
+
+        // Hooks and error middlewares see the request as the last middleware or the handler got it
+        let seen_req: Option<SeenRequest> =
+            if self.response_hooks.is_empty() && self.error_middlewares.is_empty() {
+                None
+            } else {
+                Some(Arc::new(Mutex::new(Some(our_req.clone()))))
+            };
+        let (handler, middlewares) = match &seen_req {
+            Some(seen) => {
+                let record = seen.clone();
+                let handler = Arc::new(move |req: HttpRequest, res| {
+                    *record.lock().unwrap() = Some(req.clone());
+                    handler(req, res)
+                }) as Handler;
+                let middlewares = self
+                    .middlewares
+                    .iter()
+                    .map(|middleware| {
+                        Box::new(RecordRequest {
+                            middleware: middleware.clone(),
+                            seen: seen.clone(),
+                        }) as Box<dyn Middleware>
+                    })
+                    .collect();
+                (handler, middlewares)
+            }
+            None => (handler, self.middlewares.clone()),
+        };
+
+        // The streamed body still being read when the handler responded
+        let mut pending_forward = None;
+        let mut response = match body {
+            Err(err) => our_res.error(err),
+            Ok(forward) => {
+                let run = if middlewares.is_empty() {
+                    // No middlewares, just call the handler directly
+                    handler(our_req, our_res)
+                } else {
+                    // Run the request through the middleware chain
+                    let next = Next {
+                        middleware: middlewares,
+                        handler,
+                    };
+                    Box::pin(async move { next.run(our_req, our_res).await })
+                };
+
+                match forward {
+                    None => run.await,
+                    // Feed the streamed body while the handler runs
+                    Some(forward) => match select(run, forward).await {
+                        Either::Left((response, forward)) => {
+                            pending_forward = Some(forward);
+                            response
+                        }
+                        Either::Right(((), run)) => run.await,
+                    },
+                }
+            }
+        };
+
+        let mut upgrade = None;
+        let upgrade_req = upgrade_req.lock().unwrap().take();
+        if let (Some(ws), Some(upgrade_req), Some(payload)) = (ws, upgrade_req, ws_payload) {
+            match actix_ws::handle(&req, payload) {
+                Ok((upgrade_res, session, messages)) => {
+                    let max_size = endpoint.body_limit.unwrap_or(self.body_limits.default);
+                    let socket = WebSocket::new(session, messages, max_size);
+                    actix_web::rt::spawn(ws(upgrade_req, socket));
+                    upgrade = Some(upgrade_res);
+                    response = response.status(101);
+                }
+                Err(err) => {
+                    let status = err.error_response().status().as_u16() as i32;
+                    response = response.error(RipressError::Status(status, err.to_string()));
+                }
+            }
+        }
+
+        let observed_req = seen_req.and_then(|seen| seen.lock().unwrap().take());
+        if let Some(err) = response.take_error() {
+            let next = ErrorNext {
+                middleware: self.error_middlewares.clone(),
+            };
+            let req = observed_req.clone().unwrap_or_else(HttpRequest::new);
+            response = next.run(err, req, response).await;
+        }
+
+        if let Some(observed_req) = observed_req {
+            for hook in &self.response_hooks {
+                hook(&observed_req, &response);
+            }
+        }
+
+        match upgrade {
+            // Keep the headers and cookies set by the middlewares
+            Some(mut upgrade) => {
+                for (name, value) in response.to_responder().headers() {
+                    if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+                        upgrade.headers_mut().append(name.clone(), value.clone());
+                    }
+                }
+                upgrade
+            }
+            None => {
+                let actix_res = response.into_actix_response(Some(&req));
+                match pending_forward {
+                    // Keep reading the body while the response is sent, it may stream it back
+                    Some(forward) => actix_res
+                        .map_body(|_, body| BoxBody::new(ForwardingBody::new(body, forward))),
+                    None => actix_res,
+                }
+            }
+        }
+    }
+
+    /// Runs the response hooks for a request that didn't match a route.
+    fn unmatched(
+        &self,
+        req: &actix_web::HttpRequest,
+        response: HttpResponse,
+    ) -> actix_web::HttpResponse {
+        if !self.response_hooks.is_empty() {
+            let observed_req = HttpRequest::from_actix_parts(req);
+            for hook in &self.response_hooks {
+                hook(&observed_req, &response);
+            }
+        }
+        response.into_actix_response(Some(req))
+    }
+}
+
 pub struct App {
     routes: Routes,
     middlewares: Vec<Box<dyn Middleware>>,
+    error_middlewares: Vec<Box<dyn ErrorMiddleware>>,
+    response_hooks: Vec<ResponseHook>,
+    startup_hooks: Vec<LifecycleHook>,
+    shutdown_hooks: Vec<LifecycleHook>,
+    state: StateMap,
+    body_limits: BodyLimits,
+    multipart: MultipartConfig,
 }
 
 impl App {
@@ -20,24 +381,19 @@ impl App {
         return App {
             routes: HashMap::new(),
             middlewares: Vec::new(),
+            error_middlewares: Vec::new(),
+            response_hooks: Vec::new(),
+            startup_hooks: Vec::new(),
+            shutdown_hooks: Vec::new(),
+            state: HashMap::new(),
+            body_limits: BodyLimits {
+                default: DEFAULT_BODY_LIMIT,
+                by_content_type: Vec::new(),
+            },
+            multipart: MultipartConfig::default(),
         };
     }
 
-    pub fn clone_app(&self) -> App {
-        // Create a new vector and clone each middleware box
-        let mut cloned_middlewares = Vec::new();
-
-        // Clone each middleware using clone_box
-        for middleware in &self.middlewares {
-            cloned_middlewares.push(middleware.clone_box());
-        }
-
-        App {
-            routes: self.routes.clone(),
-            middlewares: cloned_middlewares,
-        }
-    }
-
     /// Add a GET route to the application.
     ///
     /// ## Arguments
@@ -57,14 +413,13 @@ impl App {
     /// let mut app = App::new();
     /// app.get("/hello", handler);
     /// ```
-
-    pub fn get<F, Fut>(&mut self, path: &'static str, handler: F)
+    pub fn get<F, Fut>(&mut self, path: &'static str, handler: F) -> Route<'_>
     where
         F: Fn(HttpRequest, HttpResponse) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = HttpResponse> + Send + 'static,
     {
         let wrapped_handler = Arc::new(move |req, res| box_future(handler(req, res)));
-        self.add_route(HttpMethods::GET, path, wrapped_handler);
+        self.add_route(HttpMethods::GET, path, wrapped_handler)
     }
 
     /// Add a POST route to the application.
@@ -86,14 +441,13 @@ impl App {
     /// let mut app = App::new();
     /// app.post("/hello", handler);
     /// ```
-
-    pub fn post<F, Fut>(&mut self, path: &'static str, handler: F)
+    pub fn post<F, Fut>(&mut self, path: &'static str, handler: F) -> Route<'_>
     where
         F: Fn(HttpRequest, HttpResponse) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = HttpResponse> + Send + 'static,
     {
         let wrapped_handler = Arc::new(move |req, res| box_future(handler(req, res)));
-        self.add_route(HttpMethods::POST, path, wrapped_handler);
+        self.add_route(HttpMethods::POST, path, wrapped_handler)
     }
 
     /// Add a PUT route to the application.
@@ -115,14 +469,13 @@ impl App {
     /// let mut app = App::new();
     /// app.put("/hello", handler);
     /// ```
-
-    pub fn put<F, Fut>(&mut self, path: &'static str, handler: F)
+    pub fn put<F, Fut>(&mut self, path: &'static str, handler: F) -> Route<'_>
     where
         F: Fn(HttpRequest, HttpResponse) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = HttpResponse> + Send + 'static,
     {
         let wrapped_handler = Arc::new(move |req, res| box_future(handler(req, res)));
-        self.add_route(HttpMethods::PUT, path, wrapped_handler);
+        self.add_route(HttpMethods::PUT, path, wrapped_handler)
     }
 
     /// Add a DELETE route to the application.
@@ -144,14 +497,13 @@ impl App {
     /// let mut app = App::new();
     /// app.delete("/hello", handler);
     /// ```
-
-    pub fn delete<F, Fut>(&mut self, path: &'static str, handler: F)
+    pub fn delete<F, Fut>(&mut self, path: &'static str, handler: F) -> Route<'_>
     where
         F: Fn(HttpRequest, HttpResponse) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = HttpResponse> + Send + 'static,
     {
         let wrapped_handler = Arc::new(move |req, res| box_future(handler(req, res)));
-        self.add_route(HttpMethods::DELETE, path, wrapped_handler);
+        self.add_route(HttpMethods::DELETE, path, wrapped_handler)
     }
 
     /// Add a PATCH route to the application.
@@ -173,14 +525,57 @@ impl App {
     /// let mut app = App::new();
     /// app.patch("/hello", handler);
     /// ```
-
-    pub fn patch<F, Fut>(&mut self, path: &'static str, handler: F)
+    pub fn patch<F, Fut>(&mut self, path: &'static str, handler: F) -> Route<'_>
     where
         F: Fn(HttpRequest, HttpResponse) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = HttpResponse> + Send + 'static,
     {
         let wrapped_handler = Arc::new(move |req, res| box_future(handler(req, res)));
-        self.add_route(HttpMethods::PATCH, path, wrapped_handler);
+        self.add_route(HttpMethods::PATCH, path, wrapped_handler)
+    }
+
+    /// Add a WebSocket route to the application.
+    ///
+    /// GET requests to the path are upgraded to a WebSocket once the global
+    /// middlewares ran, a middleware responding instead of calling `next`
+    /// rejects the upgrade. Headers and cookies set by the middlewares are
+    /// sent with the upgrade response. Requests that aren't WebSocket
+    /// handshakes get a 426 response, invalid handshakes a 400 response.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path to the route.
+    /// * `handler` - Called with the upgrade request and the socket, the connection is closed when it returns.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::{app::App, context::HttpRequest, ws::{Message, WebSocket}};
+    ///
+    /// async fn echo(_req: HttpRequest, mut socket: WebSocket) {
+    ///     while let Some(Ok(Message::Text(text))) = socket.recv().await {
+    ///         if socket.text(text).await.is_err() {
+    ///             break;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.ws("/echo", echo);
+    /// ```
+    pub fn ws<F, Fut>(&mut self, path: &'static str, handler: F) -> Route<'_>
+    where
+        F: Fn(HttpRequest, WebSocket) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let ws: WsHandler = Arc::new(move |req, socket| Box::pin(handler(req, socket)));
+        // Only called for requests that aren't WebSocket handshakes, dispatch upgrades the others
+        let upgrade_required: Handler =
+            Arc::new(|_req, res: HttpResponse| box_future(async move { res.status(426) }));
+
+        let mut route = self.add_route(HttpMethods::GET, path, upgrade_required);
+        route.update(|endpoint| endpoint.ws = Some(ws.clone()));
+        route
     }
 
     /// Add a route to the application that matches all HTTP methods.
@@ -203,30 +598,43 @@ impl App {
     /// app.all("/hello", handler);
     ///
     /// ```
-
-    pub fn all<F, Fut>(&mut self, path: &'static str, handler: F)
+    pub fn all<F, Fut>(&mut self, path: &'static str, handler: F) -> Route<'_>
     where
         F: Fn(HttpRequest, HttpResponse) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = HttpResponse> + Send + 'static,
     {
-        let wrapped_handler = Arc::new(move |req, res| box_future(handler(req, res)));
+        let wrapped_handler: Handler = Arc::new(move |req, res| box_future(handler(req, res)));
         self.add_route(HttpMethods::GET, path, wrapped_handler.clone());
         self.add_route(HttpMethods::POST, path, wrapped_handler.clone());
         self.add_route(HttpMethods::PUT, path, wrapped_handler.clone());
         self.add_route(HttpMethods::DELETE, path, wrapped_handler.clone());
         self.add_route(HttpMethods::PATCH, path, wrapped_handler.clone());
+
+        Route {
+            app: self,
+            path,
+            methods: vec![
+                HttpMethods::GET,
+                HttpMethods::POST,
+                HttpMethods::PUT,
+                HttpMethods::DELETE,
+                HttpMethods::PATCH,
+            ],
+        }
     }
 
     /// Add a middleware to the application.
     ///
     /// ## Arguments
     ///
+    /// * `path` - The path the middleware applies to, matched on whole segments.
+    ///   Accepts a `&str` pattern or a `MiddlewarePath` with exclusions.
     /// * `middleware` - The middleware to add.
     ///
     /// ## Example
     ///
     /// ```
-    /// use ripress::app::App;
+    /// use ripress::{app::App, types::MiddlewarePath};
     /// let mut app = App::new();
     ///
     /// app.use_middleware("path", |req, res, next| {
@@ -234,19 +642,24 @@ impl App {
     ///     Box::pin(async move { next.run(req, res).await })
     /// });
     ///
+    /// app.use_middleware(MiddlewarePath::new("/api").exclude("/api/login"), |req, res, next| {
+    ///     println!("auth");
+    ///     Box::pin(async move { next.run(req, res).await })
+    /// });
+    ///
     /// ```
 
     pub fn use_middleware<F, Fut, P>(&mut self, path: P, middleware: F) -> &mut Self
     where
-        P: Into<Option<&'static str>>,
+        P: Into<MiddlewarePath>,
         F: Fn(HttpRequest, HttpResponse, Next) -> Fut + Send + Sync + Clone + 'static,
         Fut: std::future::Future<Output = HttpResponse> + Send + 'static,
     {
-        let path = path.into().unwrap_or("/").to_string();
+        let path = path.into();
 
         struct Wrapper<F> {
             func: F,
-            path: String,
+            path: MiddlewarePath,
         }
 
         impl<F, Fut> Middleware for Wrapper<F>
@@ -268,16 +681,11 @@ impl App {
                 next: Next,
             ) -> std::pin::Pin<Box<dyn std::future::Future<Output = HttpResponse> + Send + 'static>>
             {
-                if self.path == "/" {
+                if self.path.matches(req.get_path()) {
                     let fut = (self.func)(req, res, next);
                     Box::pin(fut)
                 } else {
-                    if req.get_path().starts_with(self.path.as_str()) {
-                        let fut = (self.func)(req, res, next);
-                        Box::pin(fut)
-                    } else {
-                        Box::pin(async move { next.run(req, res).await })
-                    }
+                    Box::pin(async move { next.run(req, res).await })
                 }
             }
         }
@@ -290,11 +698,325 @@ impl App {
         self
     }
 
-    /// Starts the server and listens on the specified address.
+    /// Add an error handling middleware to the application.
+    ///
+    /// Error middlewares run when the request body cannot be parsed, or when a
+    /// handler or middleware marks its response with `res.error(err)`. They run
+    /// in the order they were added, each one can respond, or pass the error
+    /// (possibly transformed) on with `next.run(err, req, res)`. After the last
+    /// one, the response gets the error's status code and message.
+    ///
+    /// The request is the one the handler, or the last middleware that ran,
+    /// was called with, so data set by earlier middlewares with `set_data` is there.
+    ///
+    /// ## Arguments
+    ///
+    /// * `middleware` - The error middleware to add.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::app::App;
+    /// use serde_json::json;
+    /// let mut app = App::new();
+    ///
+    /// app.use_error_middleware(|err, req, res, _next| async move {
+    ///     println!("{} failed: {}", req.get_path(), err);
+    ///     res.status(err.status_code()).json(json!({ "error": err.to_string() }))
+    /// });
+    ///
+    /// ```
+    pub fn use_error_middleware<F, Fut>(&mut self, middleware: F) -> &mut Self
+    where
+        F: Fn(RipressError, HttpRequest, HttpResponse, ErrorNext) -> Fut
+            + Send
+            + Sync
+            + Clone
+            + 'static,
+        Fut: std::future::Future<Output = HttpResponse> + Send + 'static,
+    {
+        struct Wrapper<F> {
+            func: F,
+        }
+
+        impl<F, Fut> ErrorMiddleware for Wrapper<F>
+        where
+            F: Fn(RipressError, HttpRequest, HttpResponse, ErrorNext) -> Fut
+                + Send
+                + Sync
+                + Clone
+                + 'static,
+            Fut: std::future::Future<Output = HttpResponse> + Send + 'static,
+        {
+            fn clone_box(&self) -> Box<dyn ErrorMiddleware> {
+                Box::new(Wrapper {
+                    func: self.func.clone(),
+                })
+            }
+
+            fn handle(
+                &self,
+                err: RipressError,
+                req: HttpRequest,
+                res: HttpResponse,
+                next: ErrorNext,
+            ) -> std::pin::Pin<Box<dyn std::future::Future<Output = HttpResponse> + Send + 'static>>
+            {
+                Box::pin((self.func)(err, req, res, next))
+            }
+        }
+
+        self.error_middlewares
+            .push(Box::new(Wrapper { func: middleware }));
+
+        self
+    }
+
+    /// Add a struct based middleware to the application.
+    ///
+    /// The middleware applies to every route. Implement `AsyncMiddleware` to
+    /// write the middleware with a plain `async fn`, or `Middleware` for full
+    /// control over the returned future.
+    ///
+    /// ## Arguments
+    ///
+    /// * `middleware` - The middleware to add.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::{app::App, context::{HttpRequest, HttpResponse}, types::{AsyncMiddleware, Next}};
+    ///
+    /// ##[derive(Clone)]
+    /// struct PoweredBy {
+    ///     name: String,
+    /// }
+    ///
+    /// impl AsyncMiddleware for PoweredBy {
+    ///     async fn handle(&self, req: HttpRequest, res: HttpResponse, next: Next) -> HttpResponse {
+    ///         next.run(req, res).await.set_header("X-Powered-By", &self.name)
+    ///     }
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.use_layer(PoweredBy { name: "ripress".to_string() });
+    /// ```
+    pub fn use_layer<M: Middleware>(&mut self, middleware: M) -> &mut Self {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+
+    /// Add a hook that is called with the final response of every routed request.
+    ///
+    /// Hooks run after the middlewares and the handler, just before the response
+    /// is sent, so they can observe the status, headers and body size of every
+    /// response, e.g. for metrics or audit logging. The request is the one the
+    /// handler, or the last middleware that ran, was called with, including the
+    /// data set by middlewares with `set_data`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `hook` - The function called with the request and the final response.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::app::App;
+    /// let mut app = App::new();
+    ///
+    /// app.on_response(|req, res| {
+    ///     println!("{} {} -> {}", req.get_method(), req.get_path(), res.get_status_code());
+    /// });
+    ///
+    /// ```
+    pub fn on_response<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn(&HttpRequest, &HttpResponse) + Send + Sync + 'static,
+    {
+        self.response_hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Add an async hook that runs before the server starts accepting connections.
+    ///
+    /// Startup hooks run in the order they were added. If a hook fails, the
+    /// server is not started and `listen` returns the error.
+    ///
+    /// ## Arguments
+    ///
+    /// * `hook` - The async function to run, e.g. to open a database pool.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::app::App;
+    /// let mut app = App::new();
+    ///
+    /// app.on_startup(|| async {
+    ///     println!("connecting to the database");
+    ///     Ok::<(), std::io::Error>(())
+    /// });
+    ///
+    /// ```
+    pub fn on_startup<F, Fut, E>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Into<HookError>,
+    {
+        self.startup_hooks.push(wrap_lifecycle_hook(hook));
+        self
+    }
+
+    /// Add an async hook that runs after the server has stopped.
+    ///
+    /// Shutdown hooks run in the order they were added, once the server stops
+    /// (e.g. on Ctrl-C). Every hook runs even if an earlier one failed, the first
+    /// error is returned from `listen`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `hook` - The async function to run, e.g. to flush buffers.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::app::App;
+    /// let mut app = App::new();
+    ///
+    /// app.on_shutdown(|| async {
+    ///     println!("flushing logs");
+    ///     Ok::<(), std::io::Error>(())
+    /// });
+    ///
+    /// ```
+    pub fn on_shutdown<F, Fut, E>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Into<HookError>,
+    {
+        self.shutdown_hooks.push(wrap_lifecycle_hook(hook));
+        self
+    }
+
+    /// Adds application state shared by every request.
+    ///
+    /// The state is looked up by type, with `req.get_state::<T>()` or the
+    /// `State<T>` handler argument. Adding a state of the same type again replaces it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `state` - The state to share.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::{app::App, context::HttpResponse, extract::State, types::handler};
+    ///
+    /// ##[derive(Clone)]
+    /// struct Config {
+    ///     greeting: String,
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.with_state(Config { greeting: "Hello".to_string() });
+    ///
+    /// app.get("/", handler(|State(config): State<Config>, res: HttpResponse| async move {
+    ///     res.ok().text(config.greeting)
+    /// }));
+    /// ```
+    pub fn with_state<T: Send + Sync + 'static>(&mut self, state: T) -> &mut Self {
+        self.state.insert(TypeId::of::<T>(), Arc::new(state));
+        self
+    }
+
+    /// Sets the default maximum request body size, in bytes.
+    ///
+    /// Larger bodies are answered with 413 Payload Too Large. Defaults to 256 KiB.
+    ///
+    /// ## Arguments
+    ///
+    /// * `limit` - The maximum body size in bytes.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::app::App;
+    ///
+    /// let mut app = App::new();
+    /// app.body_limit(1024 * 1024);
+    /// ```
+    pub fn body_limit(&mut self, limit: usize) -> &mut Self {
+        self.body_limits.default = limit;
+        self
+    }
+
+    /// Sets the maximum size of request bodies of a content type, in bytes.
+    ///
+    /// Overrides the default limit for this content type. Routes can still
+    /// override it with `Route::body_limit`.
     ///
     /// ## Arguments
     ///
-    /// * `addr` - The address to listen on e.g. "127.0.0.1:3000".
+    /// * `content_type` - The content type the limit applies to.
+    /// * `limit` - The maximum body size in bytes.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::{app::App, types::RequestBodyType};
+    ///
+    /// let mut app = App::new();
+    /// app.body_limit_for(RequestBodyType::JSON, 64 * 1024);
+    /// ```
+    pub fn body_limit_for(&mut self, content_type: RequestBodyType, limit: usize) -> &mut Self {
+        let limits = &mut self.body_limits.by_content_type;
+        limits.retain(|(body_type, _)| *body_type != content_type);
+        limits.push((content_type, limit));
+        self
+    }
+
+    /// Sets how `multipart/form-data` bodies are read.
+    ///
+    /// Sets the maximum size of uploaded files and text fields, and from which
+    /// size files are written to temporary files instead of being kept in memory.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The `MultipartConfig` to use.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::{app::App, types::{MultipartConfig, RequestBodyType}};
+    ///
+    /// let mut app = App::new();
+    /// app.body_limit_for(RequestBodyType::MULTIPART, 100 * 1024 * 1024)
+    ///     .multipart_config(MultipartConfig {
+    ///         max_file_size: 20 * 1024 * 1024,
+    ///         ..MultipartConfig::default()
+    ///     });
+    /// ```
+    pub fn multipart_config(&mut self, config: MultipartConfig) -> &mut Self {
+        self.multipart = config;
+        self
+    }
+
+    /// Starts the server and listens on the specified port.
+    ///
+    /// Startup hooks run first, then the server binds to the port and `cb` is
+    /// called with the bound address. Shutdown hooks run once the server stops.
+    ///
+    /// ## Arguments
+    ///
+    /// * `port` - The port to listen on e.g. 3000.
+    /// * `cb` - Called with the bound address once the server is listening.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(io::Error)` if a startup hook fails, the routes are invalid,
+    /// the port cannot be bound, or a shutdown hook fails.
     ///
     /// ## Example
     ///
@@ -305,74 +1027,84 @@ impl App {
     /// ##[tokio::main]
     /// async fn main() {
     ///     let mut app = App::new();
-    ///     app.listen(3000, || {println!("server running on port 3000")}).await;
+    ///     app.listen(3000, |addr| println!("server running on {}", addr)).await.unwrap();
     /// }
     ///
     /// ```
+    pub async fn listen<F: FnOnce(SocketAddr)>(self, port: i32, cb: F) -> io::Result<()> {
+        for hook in &self.startup_hooks {
+            hook()
+                .await
+                .map_err(|err| io::Error::other(format!("Startup hook failed: {}", err)))?;
+        }
 
-    pub async fn listen<F: FnOnce()>(self, port: i32, cb: F) {
-        cb();
-        let app_clone = self.clone_app();
-        actix_web::HttpServer::new(move || {
-            let mut app = actix_web::App::new();
-
-      for (path, methods) in app_clone.routes.clone() {
-        for (method, handler) in methods {
-          let method = method.clone();
-          let middlewares = app_clone.middlewares.clone();
-
-          match method {
-            HttpMethods::GET | HttpMethods::POST | HttpMethods::PUT | HttpMethods::DELETE | HttpMethods::PATCH => {
-              let route_method = match method {
-                HttpMethods::GET => actix_web::web::get(),
-                HttpMethods::POST => actix_web::web::post(),
-                HttpMethods::PUT => actix_web::web::put(),
-                HttpMethods::DELETE => actix_web::web::delete(),
-                HttpMethods::PATCH => actix_web::web::patch(),
-              };
-
-              app = app.route(
-                &path,
-                route_method.to(move |req: actix_web::HttpRequest, payload: actix_web::web::Payload| {
-                  let handler_clone = handler.clone();
-                  let middlewares_clone = middlewares.clone();
-
-                  async move {
-                    let our_req = HttpRequest::from_actix_request(req, payload).await.unwrap();
-                    let our_res = HttpResponse::new();
-                    let middleware_clone = middlewares_clone.clone();
-
-                    // If we have middlewares, run the request through them
-                    if !middlewares_clone.is_empty() {
-                      // Create a Next with our middlewares and handler
-                      let next = Next {
-                        middleware: middleware_clone,
-                        handler: handler_clone.clone(),
-                      };
-
-                      // Run the middleware chain
-                      let response = next.run(our_req, our_res).await;
-                      response.to_responder()
-                    } else {
-                      // No middlewares, just call the handler directly
-                      let future = handler_clone(our_req, our_res);
-                      let response = future.await;
-                      response.to_responder()
-                    }
-                  }
-                }),
-              );
+        // Compile the routes and middlewares once, every worker shares the same table.
+        let table = Arc::new(
+            self.build_dispatch_table()
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+        );
+
+        // The server builder is not `Send`, so it must not be held across an await.
+        let server = {
+            let server = actix_web::HttpServer::new(move || {
+                let table = table.clone();
+
+                actix_web::App::new().default_service(actix_web::web::to(
+                    move |req: actix_web::HttpRequest, payload: actix_web::web::Payload| {
+                        let table = table.clone();
+                        async move { table.dispatch(req, payload).await }
+                    },
+                ))
+            })
+            .bind(format!("127.0.0.1:{port}"))?;
+
+            if let Some(addr) = server.addrs().first() {
+                cb(*addr);
+            }
+
+            server.run()
+        };
+
+        let result = server.await;
+
+        let mut shutdown_error = None;
+        for hook in &self.shutdown_hooks {
+            if let Err(err) = hook().await {
+                shutdown_error.get_or_insert(err);
             }
-          }
         }
-      }
-     app
-    })
-      .bind(format!("127.0.0.1:{port}"))
-      .unwrap()
-      .run()
-      .await
-      .unwrap();
+
+        result?;
+        match shutdown_error {
+            Some(err) => Err(io::Error::other(format!("Shutdown hook failed: {}", err))),
+            None => Ok(()),
+        }
+    }
+
+    /// Compiles the registered routes and middlewares into an immutable dispatch table.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(RouterError)` if a route pattern is invalid or two routes
+    /// would match the same requests ambiguously.
+    pub(crate) fn build_dispatch_table(&self) -> Result<DispatchTable, RouterError> {
+        let mut router = Router::new();
+
+        for (path, handlers) in &self.routes {
+            for (method, handler) in handlers {
+                router.insert(method.clone(), path, handler.clone())?;
+            }
+        }
+
+        Ok(DispatchTable {
+            router,
+            middlewares: self.middlewares.clone(),
+            error_middlewares: self.error_middlewares.clone(),
+            response_hooks: self.response_hooks.clone(),
+            state: Arc::new(self.state.clone()),
+            body_limits: self.body_limits.clone(),
+            multipart: self.multipart.clone(),
+        })
     }
 
     /// Adds a route to the application.
@@ -383,19 +1115,46 @@ impl App {
     /// * `path` - The path of the route.
     /// * `handler` - The handler function for the route.
     ///
-    fn add_route(&mut self, method: HttpMethods, path: &'static str, handler: Handler) {
+    fn add_route(
+        &mut self,
+        method: HttpMethods,
+        path: &'static str,
+        handler: Handler,
+    ) -> Route<'_> {
         let path_handlers = self.routes.entry(path).or_insert_with(HashMap::new);
-        path_handlers.insert(method, handler);
+        path_handlers.insert(
+            method.clone(),
+            Endpoint {
+                handler,
+                body_limit: None,
+                stream_body: false,
+                ws: None,
+            },
+        );
+
+        Route {
+            app: self,
+            path,
+            methods: vec![method],
+        }
     }
 }
 
 #[cfg(test)]
 impl App {
     pub(crate) fn get_routes(&self, path: &str, method: HttpMethods) -> Option<&Handler> {
-        Some(self.routes.get(path).unwrap().get(&method).unwrap())
+        Some(&self.routes.get(path).unwrap().get(&method).unwrap().handler)
     }
 
     pub(crate) fn get_middlewares(&self) -> &Vec<Box<dyn Middleware>> {
         &self.middlewares
     }
+
+    pub(crate) fn get_error_middlewares(&self) -> &Vec<Box<dyn ErrorMiddleware>> {
+        &self.error_middlewares
+    }
+
+    pub(crate) fn get_response_hooks(&self) -> &Vec<ResponseHook> {
+        &self.response_hooks
+    }
 }
@@ -0,0 +1,262 @@
+use crate::multipart::{stream_multipart, MultipartField, MultipartStream};
+use crate::types::{Bytes, MultipartConfig, RipressError};
+use actix_web::{
+    body::{BodySize, BoxBody, MessageBody},
+    http::header::HeaderMap,
+};
+use futures_util::{
+    future::LocalBoxFuture,
+    stream::{Stream, StreamExt},
+};
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tokio::sync::{mpsc, oneshot};
+
+/// The number of chunks buffered between the connection and a `BodyStream`.
+const BODY_STREAM_CAPACITY: usize = 16;
+
+/// The request body of a streaming route, read chunk by chunk.
+///
+/// Routes stream their body when added with `Route::stream_body`, the handler
+/// takes it with `req.body_stream()` or as a `BodyStream` argument. Chunks
+/// are only read from the connection as the stream is polled, and the body
+/// limit of the route is still enforced: the stream yields
+/// `RipressError::PayloadTooLarge` once the body gets larger.
+///
+/// The body keeps being read while the response is sent, so a handler can
+/// pass the stream to `res.stream` to send it back. If the body can't be
+/// read until its end, e.g. because the response was already sent, the
+/// stream yields `RipressError::InvalidBody` instead of ending early.
+///
+/// ## Example
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use ripress::{app::App, context::HttpResponse, types::{handler, BodyStream}};
+/// use tokio::io::AsyncWriteExt;
+///
+/// async fn upload(mut body: BodyStream, res: HttpResponse) -> HttpResponse {
+///     let mut file = tokio::fs::File::create("upload.bin").await.unwrap();
+///     while let Some(chunk) = body.next().await {
+///         match chunk {
+///             Ok(chunk) => file.write_all(&chunk).await.unwrap(),
+///             Err(err) => return res.error(err),
+///         }
+///     }
+///     res.ok().text("Uploaded")
+/// }
+///
+/// let mut app = App::new();
+/// app.post("/upload", handler(upload)).stream_body().body_limit(1024 * 1024 * 1024);
+/// ```
+pub struct BodyStream {
+    chunks: StreamReceiver<Bytes>,
+}
+
+impl std::fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BodyStream").finish_non_exhaustive()
+    }
+}
+
+impl Stream for BodyStream {
+    type Item = Result<Bytes, RipressError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.chunks.poll_next(cx)
+    }
+}
+
+/// The sending side of a `StreamReceiver`.
+pub(crate) struct StreamSender<T> {
+    sender: mpsc::Sender<Result<T, RipressError>>,
+    complete: Arc<AtomicBool>,
+}
+
+impl<T> StreamSender<T> {
+    /// Sends an item, returning `false` if the receiver was dropped.
+    pub(crate) async fn send(&self, item: T) -> bool {
+        self.sender.send(Ok(item)).await.is_ok()
+    }
+
+    /// Sends an error ending the stream, returning `false` if the receiver was dropped.
+    pub(crate) async fn fail(self, err: RipressError) -> bool {
+        self.complete.store(true, Ordering::SeqCst);
+        self.sender.send(Err(err)).await.is_ok()
+    }
+
+    /// Ends the stream normally.
+    pub(crate) fn finish(self) {
+        self.complete.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Receives the items read from a request body, and an error if the body
+/// stopped being read before `StreamSender::finish` was called.
+pub(crate) struct StreamReceiver<T> {
+    receiver: mpsc::Receiver<Result<T, RipressError>>,
+    complete: Arc<AtomicBool>,
+}
+
+impl<T> StreamReceiver<T> {
+    pub(crate) fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<T, RipressError>>> {
+        match self.receiver.poll_recv(cx) {
+            // The body stopped being read before its end
+            Poll::Ready(None) if !self.complete.swap(true, Ordering::SeqCst) => {
+                Poll::Ready(Some(Err(RipressError::InvalidBody(
+                    "Request body ended before it was fully read".to_string(),
+                ))))
+            }
+            poll => poll,
+        }
+    }
+}
+
+pub(crate) fn stream_channel<T>() -> (StreamSender<T>, StreamReceiver<T>) {
+    let (sender, receiver) = mpsc::channel(BODY_STREAM_CAPACITY);
+    let complete = Arc::new(AtomicBool::new(false));
+    (
+        StreamSender {
+            sender,
+            complete: complete.clone(),
+        },
+        StreamReceiver { receiver, complete },
+    )
+}
+
+/// Creates a `BodyStream` from the receiving side of a channel.
+pub(crate) fn chunk_stream(chunks: StreamReceiver<Bytes>) -> BodyStream {
+    BodyStream { chunks }
+}
+
+/// How the handler reads the body of a streaming route.
+enum BodyReader {
+    Chunks(StreamSender<Bytes>),
+    Multipart(StreamSender<MultipartField>),
+}
+
+/// The body of a streaming route, until the handler takes it as a
+/// `BodyStream` or a `MultipartStream`.
+pub(crate) struct PendingBody {
+    reader: oneshot::Sender<BodyReader>,
+}
+
+impl std::fmt::Debug for PendingBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("PendingBody").finish_non_exhaustive()
+    }
+}
+
+impl PendingBody {
+    pub(crate) fn into_stream(self) -> BodyStream {
+        let (sender, chunks) = stream_channel();
+        let _ = self.reader.send(BodyReader::Chunks(sender));
+        chunk_stream(chunks)
+    }
+
+    pub(crate) fn into_multipart(self) -> MultipartStream {
+        let (sender, fields) = stream_channel();
+        let _ = self.reader.send(BodyReader::Multipart(sender));
+        MultipartStream::new(fields)
+    }
+}
+
+/// Creates the pending body of a streaming route, read by the returned future
+/// once the handler takes it, until it is dropped, the body ends or it
+/// exceeds `limit` bytes.
+///
+/// The payload can't leave the worker thread, so the future must be polled
+/// alongside the handler instead of being spawned.
+pub(crate) fn body_stream(
+    payload: actix_web::web::Payload,
+    limit: usize,
+    headers: HeaderMap,
+    multipart: &MultipartConfig,
+) -> (PendingBody, impl std::future::Future<Output = ()>) {
+    let (reader, chosen) = oneshot::channel();
+    let multipart = multipart.clone();
+
+    let forward = async move {
+        match chosen.await {
+            Ok(BodyReader::Chunks(chunks)) => forward_chunks(payload, limit, chunks).await,
+            Ok(BodyReader::Multipart(fields)) => {
+                stream_multipart(&headers, payload, limit, &multipart, fields).await
+            }
+            // The handler didn't take the body
+            Err(_) => {}
+        }
+    };
+
+    (PendingBody { reader }, forward)
+}
+
+async fn forward_chunks(
+    mut payload: actix_web::web::Payload,
+    limit: usize,
+    chunks: StreamSender<Bytes>,
+) {
+    let mut size = 0;
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                chunks.fail(RipressError::InvalidBody(e.to_string())).await;
+                return;
+            }
+        };
+        size += chunk.len();
+        if size > limit {
+            chunks.fail(RipressError::PayloadTooLarge(limit)).await;
+            return;
+        }
+        if !chunks.send(chunk).await {
+            return;
+        }
+    }
+    chunks.finish();
+}
+
+/// A response body that keeps reading a streamed request body while it is
+/// sent, for handlers responding before the request body ended.
+pub(crate) struct ForwardingBody {
+    body: BoxBody,
+    forward: Option<LocalBoxFuture<'static, ()>>,
+}
+
+impl ForwardingBody {
+    pub(crate) fn new(body: BoxBody, forward: LocalBoxFuture<'static, ()>) -> Self {
+        ForwardingBody {
+            body,
+            forward: Some(forward),
+        }
+    }
+}
+
+impl MessageBody for ForwardingBody {
+    type Error = Box<dyn std::error::Error>;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        if let Some(forward) = &mut self.forward {
+            if forward.as_mut().poll(cx).is_ready() {
+                self.forward = None;
+            }
+        }
+        Pin::new(&mut self.body).poll_next(cx)
+    }
+}
@@ -0,0 +1,371 @@
+//! Typed handler arguments.
+//!
+//! Handlers can take any number of arguments implementing `FromRequest`
+//! before the `HttpResponse`, e.g. `Json<T>` for the parsed body or `Path<T>`
+//! for the route parameters. Such handlers are added with `types::handler`,
+//! see `IntoHandler` for details.
+
+use crate::{
+    request::HttpRequest,
+    response::HttpResponse,
+    types::{
+        BodyStream, Bytes, IntoResponse, MultipartStream, RequestBodyContent, RequestBodyType,
+        RipressError,
+    },
+};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// A type that can be extracted from a request and used as a handler argument.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{context::HttpRequest, extract::FromRequest, types::RipressError};
+///
+/// struct ApiKey(String);
+///
+/// impl FromRequest for ApiKey {
+///     fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+///         let key = req.get_header("x-api-key")?;
+///         Ok(ApiKey(key.to_string()))
+///     }
+/// }
+/// ```
+pub trait FromRequest: Sized + Send + 'static {
+    /// Extracts the value from the request.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(RipressError)` if the value is missing or invalid.
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError>;
+}
+
+impl FromRequest for HttpRequest {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        Ok(req.clone())
+    }
+}
+
+/// The raw request body, for every content type.
+impl FromRequest for Bytes {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        Ok(req.bytes().clone())
+    }
+}
+
+/// The body of a streaming route, see `Route::stream_body`.
+///
+/// Fails with 500 Internal Server Error if the route doesn't stream its body.
+impl FromRequest for BodyStream {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        req.body_stream().ok_or_else(|| {
+            RipressError::Status(
+                500,
+                String::from("The route doesn't stream its body, see Route::stream_body"),
+            )
+        })
+    }
+}
+
+/// The fields of a streamed `multipart/form-data` body, see `MultipartStream`.
+///
+/// Fails with 415 Unsupported Media Type if the request isn't
+/// `multipart/form-data`, and 500 Internal Server Error if the route doesn't
+/// stream its body.
+impl FromRequest for MultipartStream {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        if req.body_type() != RequestBodyType::MULTIPART {
+            return Err(RipressError::Status(
+                415,
+                String::from("Expected a multipart/form-data body"),
+            ));
+        }
+        req.multipart_stream().ok_or_else(|| {
+            RipressError::Status(
+                500,
+                String::from("The route doesn't stream its body, see Route::stream_body"),
+            )
+        })
+    }
+}
+
+/// Extracts `None` instead of failing when the value is missing or invalid.
+impl<T: FromRequest> FromRequest for Option<T> {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        Ok(T::from_request(req).ok())
+    }
+}
+
+/// Passes the extraction error to the handler instead of failing the request.
+impl<T: FromRequest> FromRequest for Result<T, RipressError> {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        Ok(T::from_request(req))
+    }
+}
+
+/// Deserializes a map of strings into `T`, e.g. `{"id": "1"}` into `struct { id: u32 }`.
+fn from_string_map<T: DeserializeOwned>(
+    map: &HashMap<String, String>,
+    kind: &str,
+) -> Result<T, RipressError> {
+    let encoded = serde_urlencoded::to_string(map)
+        .map_err(|e| RipressError::Extract(format!("Invalid {}: {}", kind, e)))?;
+    serde_urlencoded::from_str(&encoded)
+        .map_err(|e| RipressError::Extract(format!("Invalid {}: {}", kind, e)))
+}
+
+/// The JSON request body, deserialized into `T`.
+///
+/// Fails if the request isn't `application/json` or the body doesn't match `T`.
+/// Returning a `Json<T>` from a handler sends `T` serialized as JSON.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{context::HttpResponse, extract::Json};
+/// use serde::Deserialize;
+///
+/// ##[derive(Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn create_user(Json(user): Json<User>, res: HttpResponse) -> HttpResponse {
+///     res.ok().text(format!("Created {}", user.name))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned + Send + 'static> FromRequest for Json<T> {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        match req.body_content() {
+            RequestBodyContent::JSON(value) => serde_json::from_value(value.clone())
+                .map(Json)
+                .map_err(|e| RipressError::Extract(format!("Invalid JSON body: {}", e))),
+            _ => Err(RipressError::Extract(String::from(
+                "Expected an application/json body",
+            ))),
+        }
+    }
+}
+
+impl<T: serde::Serialize> IntoResponse for Json<T> {
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        res.json(self.0)
+    }
+}
+
+/// The url-encoded form body, deserialized into `T`.
+///
+/// Fails if the request isn't `application/x-www-form-urlencoded` or the body
+/// doesn't match `T`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Form<T>(pub T);
+
+impl<T: DeserializeOwned + Send + 'static> FromRequest for Form<T> {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        match req.body_content() {
+            RequestBodyContent::FORM(text) => serde_urlencoded::from_str(text)
+                .map(Form)
+                .map_err(|e| RipressError::Extract(format!("Invalid form body: {}", e))),
+            _ => Err(RipressError::Extract(String::from(
+                "Expected an application/x-www-form-urlencoded body",
+            ))),
+        }
+    }
+}
+
+/// The query parameters, deserialized into `T` like `req.query` does.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{context::HttpResponse, extract::Query};
+/// use serde::Deserialize;
+///
+/// ##[derive(Deserialize)]
+/// struct Pagination {
+///     page: u32,
+///     per_page: Option<u32>,
+/// }
+///
+/// async fn list(Query(query): Query<Pagination>, res: HttpResponse) -> HttpResponse {
+///     res.ok().text(format!("Page {} of size {}", query.page, query.per_page.unwrap_or(20)))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query<T>(pub T);
+
+impl<T: DeserializeOwned + Send + 'static> FromRequest for Query<T> {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        req.query().map(Query).map_err(RipressError::from)
+    }
+}
+
+/// The route parameters, deserialized into `T`.
+///
+/// `T` is deserialized from a map of parameter names to values, so it's
+/// usually a struct with a field per parameter, or a `HashMap`.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{context::HttpResponse, extract::Path};
+/// use serde::Deserialize;
+///
+/// ##[derive(Deserialize)]
+/// struct PostPath {
+///     user_id: u32,
+///     post_id: u32,
+/// }
+///
+/// // Registered as "/user/{user_id}/post/{post_id}"
+/// async fn get_post(Path(path): Path<PostPath>, res: HttpResponse) -> HttpResponse {
+///     res.ok().text(format!("Post {} of user {}", path.post_id, path.user_id))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path<T>(pub T);
+
+impl<T: DeserializeOwned + Send + 'static> FromRequest for Path<T> {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        from_string_map(req.params_map(), "route parameters").map(Path)
+    }
+}
+
+/// A clone of the application state of type `T`, added with `App::with_state`.
+///
+/// Fails with 500 Internal Server Error if no state of type `T` was added.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, context::HttpResponse, extract::State, types::handler};
+/// use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+///
+/// ##[derive(Clone)]
+/// struct Counter(Arc<AtomicUsize>);
+///
+/// async fn visit(State(counter): State<Counter>, res: HttpResponse) -> HttpResponse {
+///     let visits = counter.0.fetch_add(1, Ordering::SeqCst) + 1;
+///     res.ok().text(format!("Visit number {}", visits))
+/// }
+///
+/// let mut app = App::new();
+/// app.with_state(Counter(Arc::new(AtomicUsize::new(0))));
+/// app.get("/", handler(visit));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct State<T>(pub T);
+
+impl<T: Clone + Send + Sync + 'static> FromRequest for State<T> {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        match req.get_state::<T>() {
+            Some(state) => Ok(State(state.clone())),
+            None => Err(RipressError::Status(
+                500,
+                format!("No state of type {}", std::any::type_name::<T>()),
+            )),
+        }
+    }
+}
+
+/// A header that can be extracted with `Header<H>`.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::extract::TypedHeader;
+///
+/// struct RequestId(u64);
+///
+/// impl TypedHeader for RequestId {
+///     const NAME: &'static str = "x-request-id";
+///
+///     fn parse(value: &str) -> Result<Self, String> {
+///         value.parse().map(RequestId).map_err(|_| String::from("Invalid request id"))
+///     }
+/// }
+/// ```
+pub trait TypedHeader: Sized + Send + 'static {
+    /// The name of the header, case-insensitive.
+    const NAME: &'static str;
+
+    /// Parses the value of the header.
+    fn parse(value: &str) -> Result<Self, String>;
+}
+
+/// A typed request header.
+///
+/// Fails if the header is missing or cannot be parsed, use `Option<Header<H>>`
+/// for optional headers.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{context::HttpResponse, extract::{Header, UserAgent}};
+///
+/// async fn handler(Header(agent): Header<UserAgent>, res: HttpResponse) -> HttpResponse {
+///     res.ok().text(format!("Hello {}", agent.0))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header<H>(pub H);
+
+impl<H: TypedHeader> FromRequest for Header<H> {
+    fn from_request(req: &HttpRequest) -> Result<Self, RipressError> {
+        let value = req.get_header(H::NAME)?;
+        H::parse(value).map(Header).map_err(|reason| {
+            RipressError::Extract(format!("Invalid header {}: {}", H::NAME, reason))
+        })
+    }
+}
+
+macro_rules! string_header {
+    ($(#[$doc:meta])* $name:ident, $header:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name(pub String);
+
+        impl TypedHeader for $name {
+            const NAME: &'static str = $header;
+
+            fn parse(value: &str) -> Result<Self, String> {
+                Ok($name(value.to_string()))
+            }
+        }
+    };
+}
+
+string_header!(
+    /// The `User-Agent` request header.
+    UserAgent,
+    "user-agent"
+);
+
+string_header!(
+    /// The `Host` request header.
+    Host,
+    "host"
+);
+
+string_header!(
+    /// The `Authorization` request header.
+    Authorization,
+    "authorization"
+);
+
+string_header!(
+    /// The `Last-Event-ID` request header, sent by reconnecting `EventSource` clients.
+    LastEventId,
+    "last-event-id"
+);
+
+impl Authorization {
+    /// Returns the token of a `Bearer` authorization.
+    pub fn bearer(&self) -> Option<&str> {
+        self.0.strip_prefix("Bearer ")
+    }
+}
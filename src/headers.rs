@@ -0,0 +1,155 @@
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// An error adding a header with an invalid name or value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderError {
+    InvalidName(String),
+    InvalidValue(String),
+}
+
+impl std::fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HeaderError::InvalidName(name) => write!(f, "Invalid header name {}", name),
+            HeaderError::InvalidValue(name) => write!(f, "Invalid value for header {}", name),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+/// A multi-valued map of HTTP headers, used by both requests and responses.
+///
+/// Header names are case-insensitive, and a header can have several values,
+/// e.g. multiple `Link` headers. Values are bytes and may not be valid text,
+/// the `&str` getters skip those.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::types::Headers;
+///
+/// let mut headers = Headers::new();
+/// headers.insert("Link", "</style.css>; rel=preload").unwrap();
+/// headers.append("link", "</app.js>; rel=preload").unwrap();
+///
+/// assert_eq!(headers.get("LINK"), Some("</style.css>; rel=preload"));
+/// assert_eq!(headers.get_all("link").len(), 2);
+///
+/// headers.remove("Link");
+/// assert!(!headers.contains("link"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Headers {
+    inner: HeaderMap,
+}
+
+fn to_name(name: &str) -> Result<HeaderName, HeaderError> {
+    HeaderName::from_bytes(name.as_bytes()).map_err(|_| HeaderError::InvalidName(name.to_string()))
+}
+
+fn to_value(name: &str, value: &[u8]) -> Result<HeaderValue, HeaderError> {
+    HeaderValue::from_bytes(value).map_err(|_| HeaderError::InvalidValue(name.to_string()))
+}
+
+impl Headers {
+    /// Creates an empty header map.
+    pub fn new() -> Self {
+        Headers {
+            inner: HeaderMap::new(),
+        }
+    }
+
+    /// Returns the first value of the header, if it is valid text.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_bytes(name)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    /// Returns the first value of the header as bytes.
+    pub fn get_bytes(&self, name: &str) -> Option<&[u8]> {
+        let name = to_name(name).ok()?;
+        self.inner.get(name).map(HeaderValue::as_bytes)
+    }
+
+    /// Returns every value of the header that is valid text, in order.
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.get_all_bytes(name)
+            .into_iter()
+            .filter_map(|value| std::str::from_utf8(value).ok())
+            .collect()
+    }
+
+    /// Returns every value of the header as bytes, in order.
+    pub fn get_all_bytes(&self, name: &str) -> Vec<&[u8]> {
+        match to_name(name) {
+            Ok(name) => self
+                .inner
+                .get_all(name)
+                .map(HeaderValue::as_bytes)
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the header has at least one value.
+    pub fn contains(&self, name: &str) -> bool {
+        to_name(name).is_ok_and(|name| self.inner.contains_key(name))
+    }
+
+    /// Sets the header, replacing all of its previous values.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(HeaderError)` if the name or value isn't a valid header.
+    pub fn insert(&mut self, name: &str, value: impl AsRef<[u8]>) -> Result<(), HeaderError> {
+        let value = to_value(name, value.as_ref())?;
+        self.inner.insert(to_name(name)?, value);
+        Ok(())
+    }
+
+    /// Adds a value to the header, keeping its previous values.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(HeaderError)` if the name or value isn't a valid header.
+    pub fn append(&mut self, name: &str, value: impl AsRef<[u8]>) -> Result<(), HeaderError> {
+        let value = to_value(name, value.as_ref())?;
+        self.inner.append(to_name(name)?, value);
+        Ok(())
+    }
+
+    /// Removes all values of the header, returning `true` if there were any.
+    pub fn remove(&mut self, name: &str) -> bool {
+        match to_name(name) {
+            Ok(name) => self.inner.remove(name).next().is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the number of values, counting each value of a header.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Iterates over every header value, with lowercase names.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.inner
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_bytes()))
+    }
+
+    pub(crate) fn as_header_map(&self) -> &HeaderMap {
+        &self.inner
+    }
+}
+
+impl From<HeaderMap> for Headers {
+    fn from(inner: HeaderMap) -> Self {
+        Headers { inner }
+    }
+}
@@ -0,0 +1,375 @@
+//! An in-process pub/sub hub, to fan messages out to live connections.
+//!
+//! Share a `Hub` with `App::with_state`, subscribe in WebSocket and
+//! Server-Sent Events handlers, and publish from any handler with the `State`
+//! extractor. Subscribers join rooms to receive the messages broadcast to
+//! them.
+
+use futures_util::stream::Stream;
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// The number of messages buffered for each subscriber, by default.
+const DEFAULT_CAPACITY: usize = 64;
+
+/// Identifies a subscriber of a `Hub`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientId(u64);
+
+impl std::fmt::Display for ClientId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// What a `Hub` does when broadcasting to a subscriber whose buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// Skips the message for that subscriber.
+    DropMessage,
+    /// Disconnects the subscriber, its subscription ends after the buffered
+    /// messages so the client can reconnect and catch up.
+    #[default]
+    Disconnect,
+}
+
+/// An error sending to a single subscriber.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HubError {
+    /// The subscriber doesn't exist or has disconnected.
+    ClientNotFound(ClientId),
+}
+
+impl std::fmt::Display for HubError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HubError::ClientNotFound(id) => write!(f, "Client {} isn't connected", id),
+        }
+    }
+}
+
+impl std::error::Error for HubError {}
+
+struct Client<T> {
+    sender: mpsc::Sender<T>,
+    rooms: HashSet<String>,
+}
+
+struct HubState<T> {
+    next_id: u64,
+    clients: HashMap<ClientId, Client<T>>,
+    rooms: HashMap<String, HashSet<ClientId>>,
+}
+
+impl<T> HubState<T> {
+    fn remove(&mut self, id: ClientId) {
+        if let Some(client) = self.clients.remove(&id) {
+            for room in client.rooms {
+                self.leave_room(&room, id);
+            }
+        }
+    }
+
+    fn leave_room(&mut self, room: &str, id: ClientId) {
+        if let Some(members) = self.rooms.get_mut(room) {
+            members.remove(&id);
+            if members.is_empty() {
+                self.rooms.remove(room);
+            }
+        }
+    }
+}
+
+/// A pub/sub hub, delivering messages to its subscribers.
+///
+/// Cloning a hub is cheap, clones share the same subscribers. Every
+/// subscriber has a bounded buffer: broadcasts never wait for slow
+/// subscribers, they are handled according to the hub's `Backpressure`.
+///
+/// ## Example
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use ripress::{
+///     app::App,
+///     context::{HttpRequest, HttpResponse},
+///     extract::State,
+///     hub::Hub,
+///     sse::Event,
+///     types::handler,
+/// };
+///
+/// async fn events(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+///     let hub = req.get_state::<Hub<String>>().unwrap();
+///     let subscription = hub.subscribe();
+///     subscription.join(req.get_params("room").unwrap());
+///     res.ok().sse(subscription.map(|message| Event::new().data(message)))
+/// }
+///
+/// async fn publish(req: HttpRequest, State(hub): State<Hub<String>>) -> String {
+///     let room = req.get_params("room").unwrap();
+///     let sent = hub.broadcast_to(room, req.text().unwrap().to_string());
+///     format!("Sent to {} clients", sent)
+/// }
+///
+/// let mut app = App::new();
+/// app.with_state(Hub::<String>::new());
+/// app.get("/rooms/{room}/events", events);
+/// app.post("/rooms/{room}/messages", handler(publish));
+/// ```
+pub struct Hub<T> {
+    state: Arc<Mutex<HubState<T>>>,
+    capacity: usize,
+    backpressure: Backpressure,
+}
+
+impl<T> Clone for Hub<T> {
+    fn clone(&self) -> Self {
+        Hub {
+            state: self.state.clone(),
+            capacity: self.capacity,
+            backpressure: self.backpressure,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Hub<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Hub")
+            .field("capacity", &self.capacity)
+            .field("backpressure", &self.backpressure)
+            .field("clients", &self.state.lock().unwrap().clients.len())
+            .finish()
+    }
+}
+
+impl<T: Clone + Send + 'static> Default for Hub<T> {
+    fn default() -> Self {
+        Hub::new()
+    }
+}
+
+impl<T: Clone + Send + 'static> Hub<T> {
+    /// Creates a hub buffering 64 messages per subscriber, disconnecting slower ones.
+    pub fn new() -> Self {
+        Hub::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Creates a hub buffering `capacity` messages per subscriber.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "Hub capacity must be greater than 0");
+        Hub {
+            state: Arc::new(Mutex::new(HubState {
+                next_id: 0,
+                clients: HashMap::new(),
+                rooms: HashMap::new(),
+            })),
+            capacity,
+            backpressure: Backpressure::default(),
+        }
+    }
+
+    /// Sets what happens to subscribers whose buffer is full.
+    pub fn backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Adds a subscriber, it is removed from the hub when the subscription is dropped.
+    pub fn subscribe(&self) -> Subscription<T> {
+        let (sender, receiver) = mpsc::channel(self.capacity);
+        let mut state = self.state.lock().unwrap();
+        let id = ClientId(state.next_id);
+        state.next_id += 1;
+        state.clients.insert(
+            id,
+            Client {
+                sender,
+                rooms: HashSet::new(),
+            },
+        );
+
+        Subscription {
+            id,
+            receiver,
+            hub: self.clone(),
+        }
+    }
+
+    /// Adds a subscriber to a room.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `false` if the subscriber isn't connected.
+    pub fn join(&self, id: ClientId, room: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.clients.get_mut(&id) {
+            Some(client) => {
+                client.rooms.insert(room.to_string());
+                state.rooms.entry(room.to_string()).or_default().insert(id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes a subscriber from a room.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `false` if the subscriber wasn't in the room.
+    pub fn leave(&self, id: ClientId, room: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let left = state
+            .clients
+            .get_mut(&id)
+            .is_some_and(|client| client.rooms.remove(room));
+        if left {
+            state.leave_room(room, id);
+        }
+        left
+    }
+
+    /// Disconnects a subscriber, its subscription ends after the buffered messages.
+    pub fn disconnect(&self, id: ClientId) {
+        self.state.lock().unwrap().remove(id);
+    }
+
+    /// Sends a message to every subscriber.
+    ///
+    /// ## Returns
+    ///
+    /// Returns the number of subscribers the message was delivered to.
+    pub fn broadcast(&self, message: T) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let ids: Vec<ClientId> = state.clients.keys().copied().collect();
+        self.deliver(&mut state, ids, message)
+    }
+
+    /// Sends a message to the subscribers in a room.
+    ///
+    /// ## Returns
+    ///
+    /// Returns the number of subscribers the message was delivered to.
+    pub fn broadcast_to(&self, room: &str, message: T) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let ids: Vec<ClientId> = match state.rooms.get(room) {
+            Some(members) => members.iter().copied().collect(),
+            None => return 0,
+        };
+        self.deliver(&mut state, ids, message)
+    }
+
+    /// Sends a message to a single subscriber, waiting for room in its buffer.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(HubError::ClientNotFound)` if the subscriber isn't connected.
+    pub async fn send(&self, id: ClientId, message: T) -> Result<(), HubError> {
+        let sender = match self.state.lock().unwrap().clients.get(&id) {
+            Some(client) => client.sender.clone(),
+            None => return Err(HubError::ClientNotFound(id)),
+        };
+        sender
+            .send(message)
+            .await
+            .map_err(|_| HubError::ClientNotFound(id))
+    }
+
+    /// Returns the number of connected subscribers.
+    pub fn client_count(&self) -> usize {
+        self.state.lock().unwrap().clients.len()
+    }
+
+    /// Returns the subscribers in a room.
+    pub fn members(&self, room: &str) -> Vec<ClientId> {
+        self.state
+            .lock()
+            .unwrap()
+            .rooms
+            .get(room)
+            .map(|members| members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn deliver(&self, state: &mut HubState<T>, ids: Vec<ClientId>, message: T) -> usize {
+        let mut delivered = 0;
+        for id in ids {
+            let result = match state.clients.get(&id) {
+                Some(client) => client.sender.try_send(message.clone()),
+                None => continue,
+            };
+            match result {
+                Ok(()) => delivered += 1,
+                Err(TrySendError::Full(_)) if self.backpressure == Backpressure::DropMessage => {}
+                Err(_) => state.remove(id),
+            }
+        }
+        delivered
+    }
+}
+
+/// A subscriber of a `Hub`, receiving its messages as a `Stream`.
+///
+/// The subscriber leaves its rooms and the hub when the subscription is
+/// dropped, e.g. when the client of an SSE response disconnects.
+pub struct Subscription<T> {
+    id: ClientId,
+    receiver: mpsc::Receiver<T>,
+    hub: Hub<T>,
+}
+
+impl<T> std::fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Clone + Send + 'static> Subscription<T> {
+    /// Returns the id of the subscriber, to send it messages with `Hub::send`.
+    pub fn id(&self) -> ClientId {
+        self.id
+    }
+
+    /// Joins a room, see `Hub::join`.
+    pub fn join(&self, room: &str) -> bool {
+        self.hub.join(self.id, room)
+    }
+
+    /// Leaves a room, see `Hub::leave`.
+    pub fn leave(&self, room: &str) -> bool {
+        self.hub.leave(self.id, room)
+    }
+
+    /// Receives the next message, `None` once the subscriber is disconnected.
+    pub async fn recv(&mut self) -> Option<T> {
+        self.receiver.recv().await
+    }
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.hub.state.lock() {
+            state.remove(self.id);
+        }
+    }
+}
@@ -1,8 +1,16 @@
 pub mod app;
+mod body_stream;
+pub mod extract;
+pub mod hub;
 
 // HttpRequest and HttpResponse
+mod headers;
+mod media_type;
+mod multipart;
+mod query;
 mod request;
 mod response;
+mod router;
 mod tests;
 
 pub mod context {
@@ -11,4 +19,6 @@ pub mod context {
 }
 
 pub mod middlewares;
+pub mod sse;
 pub mod types;
+pub mod ws;
@@ -0,0 +1,163 @@
+/// A parsed media type, e.g. the value of a `Content-Type` header.
+///
+/// The type, subtype and parameter names are case-insensitive and stored in
+/// lowercase. Parameter values keep their case, with quotes removed.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::types::MediaType;
+///
+/// let media_type = MediaType::parse("Application/vnd.api+JSON; charset=\"UTF-8\"").unwrap();
+/// assert_eq!(media_type.essence(), "application/vnd.api+json");
+/// assert_eq!(media_type.suffix(), Some("json"));
+/// assert_eq!(media_type.charset(), Some("UTF-8"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaType {
+    essence: String,
+    slash: usize,
+    params: Vec<(String, String)>,
+}
+
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+/// Splits a header value on `;`, ignoring the ones in quoted strings.
+fn split_params(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (index, char) in value.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(quoted) => {
+            let mut unquoted = String::with_capacity(quoted.len());
+            let mut chars = quoted.chars();
+            while let Some(char) = chars.next() {
+                match char {
+                    '\\' => unquoted.extend(chars.next()),
+                    _ => unquoted.push(char),
+                }
+            }
+            unquoted
+        }
+        None => value.to_string(),
+    }
+}
+
+impl MediaType {
+    /// Parses a media type with its parameters.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `None` if the value isn't a `type/subtype` media type. Invalid
+    /// parameters are skipped.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = split_params(value).into_iter();
+        let essence = parts.next()?.trim().to_ascii_lowercase();
+        let (type_, subtype) = essence.split_once('/')?;
+        if !is_token(type_) || !is_token(subtype) {
+            return None;
+        }
+        let slash = type_.len();
+
+        let params = parts
+            .filter_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                let name = name.trim();
+                if !is_token(name) {
+                    return None;
+                }
+                Some((name.to_ascii_lowercase(), unquote(value.trim())))
+            })
+            .collect();
+
+        Some(MediaType {
+            essence,
+            slash,
+            params,
+        })
+    }
+
+    /// Returns the media type without parameters, e.g. `application/json`.
+    pub fn essence(&self) -> &str {
+        &self.essence
+    }
+
+    /// Returns the top-level type, e.g. `application`.
+    pub fn type_(&self) -> &str {
+        &self.essence[..self.slash]
+    }
+
+    /// Returns the subtype, e.g. `vnd.api+json`.
+    pub fn subtype(&self) -> &str {
+        &self.essence[self.slash + 1..]
+    }
+
+    /// Returns the structured syntax suffix of the subtype, e.g. `json` for `vnd.api+json`.
+    pub fn suffix(&self) -> Option<&str> {
+        self.subtype().rsplit_once('+').map(|(_, suffix)| suffix)
+    }
+
+    /// Returns the value of a parameter, the name is case-insensitive.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the `charset` parameter, e.g. `utf-8`.
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    /// Returns `true` for JSON media types, `application/json` and `+json` subtypes.
+    pub fn is_json(&self) -> bool {
+        self.essence == "application/json" || self.suffix() == Some("json")
+    }
+}
+
+impl std::fmt::Display for MediaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.essence)?;
+        for (name, value) in &self.params {
+            if is_token(value) {
+                write!(f, "; {}={}", name, value)?;
+            } else {
+                write!(
+                    f,
+                    "; {}=\"{}\"",
+                    name,
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{
+    context::HttpResponse,
+    request::HttpRequest,
+    types::{Fut, Middleware, Next},
+};
+use std::{future::Future, sync::Arc};
+
+/// A middleware built from a closure, see [`from_fn`].
+#[derive(Clone)]
+pub struct FromFn<F> {
+    func: F,
+}
+
+/// Turns a closure middleware into a `Middleware`, so it can be passed to
+/// combinators or `App::use_layer`.
+///
+/// ## Arguments
+///
+/// * `func` - The middleware closure, e.g. the one returned by `cors(None)`
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, middlewares::{cors::cors, from_fn}};
+/// let mut app = App::new();
+/// app.use_layer(from_fn(cors(None)));
+/// ```
+pub fn from_fn<F, Fut>(func: F) -> FromFn<F>
+where
+    F: Fn(HttpRequest, HttpResponse, Next) -> Fut + Send + Sync + Clone + 'static,
+    Fut: Future<Output = HttpResponse> + Send + 'static,
+{
+    FromFn { func }
+}
+
+impl<F, F2> Middleware for FromFn<F>
+where
+    F: Fn(HttpRequest, HttpResponse, Next) -> F2 + Send + Sync + Clone + 'static,
+    F2: Future<Output = HttpResponse> + Send + 'static,
+{
+    fn handle(&self, req: HttpRequest, res: HttpResponse, next: Next) -> Fut {
+        Box::pin((self.func)(req, res, next))
+    }
+
+    fn clone_box(&self) -> Box<dyn Middleware> {
+        Box::new(self.clone())
+    }
+}
+
+/// A middleware that only runs when a predicate holds, see [`when`] and [`unless`].
+pub struct When<P> {
+    predicate: Arc<P>,
+    middleware: Box<dyn Middleware>,
+    expected: bool,
+}
+
+/// Runs the middleware only for requests matching the predicate, other
+/// requests go straight to the next middleware.
+///
+/// ## Arguments
+///
+/// * `predicate` - Decides if the middleware runs for a request
+/// * `middleware` - The middleware to run
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, middlewares::{from_fn, logger::logger, when}, types::RequestBodyType};
+/// let mut app = App::new();
+/// app.use_layer(when(|req| req.is(RequestBodyType::JSON), from_fn(logger(None))));
+/// ```
+pub fn when<P, M>(predicate: P, middleware: M) -> When<P>
+where
+    P: Fn(&HttpRequest) -> bool + Send + Sync + 'static,
+    M: Middleware,
+{
+    When {
+        predicate: Arc::new(predicate),
+        middleware: Box::new(middleware),
+        expected: true,
+    }
+}
+
+/// Runs the middleware only for requests not matching the predicate.
+///
+/// ## Arguments
+///
+/// * `predicate` - Decides if the middleware is skipped for a request
+/// * `middleware` - The middleware to run
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, middlewares::{from_fn, logger::logger, unless}};
+/// let mut app = App::new();
+/// app.use_layer(unless(|req| req.get_header("X-Internal").is_ok(), from_fn(logger(None))));
+/// ```
+pub fn unless<P, M>(predicate: P, middleware: M) -> When<P>
+where
+    P: Fn(&HttpRequest) -> bool + Send + Sync + 'static,
+    M: Middleware,
+{
+    When {
+        predicate: Arc::new(predicate),
+        middleware: Box::new(middleware),
+        expected: false,
+    }
+}
+
+impl<P> Middleware for When<P>
+where
+    P: Fn(&HttpRequest) -> bool + Send + Sync + 'static,
+{
+    fn handle(&self, req: HttpRequest, res: HttpResponse, next: Next) -> Fut {
+        if (self.predicate)(&req) == self.expected {
+            self.middleware.handle(req, res, next)
+        } else {
+            Box::pin(next.run(req, res))
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Middleware> {
+        Box::new(When {
+            predicate: self.predicate.clone(),
+            middleware: self.middleware.clone_box(),
+            expected: self.expected,
+        })
+    }
+}
+
+/// Several middlewares running in order as one, see [`compose`].
+#[derive(Clone)]
+pub struct Compose {
+    middlewares: Vec<Box<dyn Middleware>>,
+}
+
+/// Combines middlewares into a single middleware running them in order.
+///
+/// ## Arguments
+///
+/// * `middlewares` - The middlewares to run, in order
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, middlewares::{compose, cors::cors, from_fn, logger::logger, when}};
+/// let mut app = App::new();
+/// app.use_layer(when(
+///     |req| req.get_header("X-Debug").is_ok(),
+///     compose([from_fn(logger(None))]),
+/// ));
+/// ```
+pub fn compose<I, M>(middlewares: I) -> Compose
+where
+    I: IntoIterator<Item = M>,
+    M: Middleware,
+{
+    Compose {
+        middlewares: middlewares
+            .into_iter()
+            .map(|middleware| Box::new(middleware) as Box<dyn Middleware>)
+            .collect(),
+    }
+}
+
+impl Middleware for Compose {
+    fn handle(&self, req: HttpRequest, res: HttpResponse, next: Next) -> Fut {
+        // Run the composed middlewares first, then continue with the rest of the chain
+        let mut middleware = self.middlewares.clone();
+        middleware.extend(next.middleware);
+
+        let next = Next {
+            middleware,
+            handler: next.handler,
+        };
+        Box::pin(next.run(req, res))
+    }
+
+    fn clone_box(&self) -> Box<dyn Middleware> {
+        Box::new(self.clone())
+    }
+}
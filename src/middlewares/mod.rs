@@ -1,2 +1,5 @@
+mod combinators;
 pub mod cors;
 pub mod logger;
+
+pub use combinators::{compose, from_fn, unless, when, Compose, FromFn, When};
@@ -0,0 +1,500 @@
+use crate::body_stream::{chunk_stream, stream_channel, StreamReceiver, StreamSender};
+use crate::types::{BodyStream, Bytes, RipressError};
+use actix_web::{error::PayloadError, http::header::HeaderMap, web::BytesMut};
+use futures_util::stream::{Stream, StreamExt};
+use std::{
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tempfile::{NamedTempFile, TempPath};
+use tokio::io::AsyncWriteExt;
+
+/// How `multipart/form-data` bodies are read, set with `App::multipart_config`.
+///
+/// The whole body is still limited by `App::body_limit`, use
+/// `App::body_limit_for(RequestBodyType::MULTIPART, ...)` to allow larger uploads.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, types::MultipartConfig};
+///
+/// let mut app = App::new();
+/// app.multipart_config(MultipartConfig {
+///     max_file_size: 50 * 1024 * 1024,
+///     ..MultipartConfig::default()
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct MultipartConfig {
+    /// The maximum size of each uploaded file, in bytes. Defaults to 10 MiB.
+    pub max_file_size: usize,
+    /// The maximum size of each text field, in bytes. Defaults to 1 MiB.
+    pub max_field_size: usize,
+    /// Files larger than this, in bytes, are written to a temporary file
+    /// instead of being kept in memory. Defaults to 64 KiB.
+    pub memory_threshold: usize,
+    /// The directory of the temporary files, the system's temporary directory if `None`.
+    pub temp_dir: Option<PathBuf>,
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        MultipartConfig {
+            max_file_size: 10 * 1024 * 1024,
+            max_field_size: 1024 * 1024,
+            memory_threshold: 64 * 1024,
+            temp_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum FileData {
+    Memory(Bytes),
+    Disk(Arc<TempPath>),
+}
+
+/// A file uploaded in a `multipart/form-data` body.
+///
+/// Small files are kept in memory, larger ones in a temporary file that is
+/// deleted once the request and every clone of the file are dropped.
+///
+/// ## Example
+///
+/// ```no_run
+/// use ripress::context::{HttpRequest, HttpResponse};
+///
+/// async fn upload(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+///     for file in req.files() {
+///         let name = file.file_name().unwrap_or("upload.bin");
+///         if let Err(e) = file.save_to(format!("uploads/{}", name)).await {
+///             return res.internal_server_error().text(e.to_string());
+///         }
+///     }
+///     res.ok().text(format!("Saved {} files", req.files().len()))
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UploadedFile {
+    field_name: String,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    size: usize,
+    data: FileData,
+}
+
+impl UploadedFile {
+    /// Returns the name of the form field the file was sent in.
+    pub fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    /// Returns the file name sent by the client.
+    ///
+    /// The name is chosen by the client, don't use it as a path without checking it.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Returns the content type sent by the client, e.g. `image/png`.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Returns the size of the file in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the path of the temporary file, or `None` if the file is kept in memory.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.data {
+            FileData::Memory(_) => None,
+            FileData::Disk(path) => Some(path),
+        }
+    }
+
+    /// Returns the contents of the file, reading it from disk if it was spooled.
+    pub async fn bytes(&self) -> std::io::Result<Bytes> {
+        match &self.data {
+            FileData::Memory(bytes) => Ok(bytes.clone()),
+            FileData::Disk(path) => tokio::fs::read(path.as_ref() as &Path)
+                .await
+                .map(Bytes::from),
+        }
+    }
+
+    /// Copies the file to `path`.
+    pub async fn save_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        match &self.data {
+            FileData::Memory(bytes) => tokio::fs::write(path, bytes).await,
+            FileData::Disk(temp_path) => tokio::fs::copy(temp_path.as_ref() as &Path, path)
+                .await
+                .map(|_| ()),
+        }
+    }
+}
+
+/// The text fields and files of a `multipart/form-data` body.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::context::HttpRequest;
+///
+/// let req = HttpRequest::new();
+/// if let Ok(form) = req.multipart() {
+///     println!("Title: {:?}", form.field("title"));
+///     println!("Avatar: {:?}", form.file("avatar").and_then(|file| file.file_name()));
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Multipart {
+    fields: Vec<(String, String)>,
+    files: Vec<UploadedFile>,
+}
+
+impl Multipart {
+    /// Returns the first value of the text field.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterates over every text field in the order they were sent.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the first file sent in the field.
+    pub fn file(&self, name: &str) -> Option<&UploadedFile> {
+        self.files.iter().find(|file| file.field_name == name)
+    }
+
+    /// Returns every file in the order they were sent.
+    pub fn files(&self) -> &[UploadedFile] {
+        &self.files
+    }
+}
+
+/// The fields of a `multipart/form-data` body, received one by one as the body streams in.
+///
+/// Available on routes added with `Route::stream_body`, with
+/// `req.multipart_stream()` or as a `MultipartStream` argument. The handler
+/// can reject a field before the rest of the body is read, dropping the
+/// stream stops reading the body. Each field must be read or dropped before
+/// the next one is received.
+///
+/// The body limit of the route, `MultipartConfig::max_file_size` and `MultipartConfig::max_field_size` are
+/// enforced, files aren't kept in memory or written to temporary files.
+///
+/// ## Example
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use ripress::{app::App, context::HttpResponse, types::{handler, MultipartStream}};
+/// use tokio::io::AsyncWriteExt;
+///
+/// async fn upload(mut form: MultipartStream, res: HttpResponse) -> HttpResponse {
+///     while let Some(field) = form.next_field().await {
+///         let mut field = match field {
+///             Ok(field) => field,
+///             Err(err) => return res.error(err),
+///         };
+///         if field.content_type() != Some("image/png") {
+///             return res.bad_request().text(format!("{} isn't a PNG", field.name()));
+///         }
+///
+///         let mut file = tokio::fs::File::create("upload.png").await.unwrap();
+///         while let Some(chunk) = field.next().await {
+///             match chunk {
+///                 Ok(chunk) => file.write_all(&chunk).await.unwrap(),
+///                 Err(err) => return res.error(err),
+///             }
+///         }
+///     }
+///     res.ok().text("Uploaded")
+/// }
+///
+/// let mut app = App::new();
+/// app.post("/upload", handler(upload)).stream_body();
+/// ```
+pub struct MultipartStream {
+    fields: StreamReceiver<MultipartField>,
+}
+
+impl std::fmt::Debug for MultipartStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("MultipartStream").finish_non_exhaustive()
+    }
+}
+
+impl MultipartStream {
+    pub(crate) fn new(fields: StreamReceiver<MultipartField>) -> Self {
+        MultipartStream { fields }
+    }
+
+    /// Receives the next field, `None` once the body ended.
+    pub async fn next_field(&mut self) -> Option<Result<MultipartField, RipressError>> {
+        self.next().await
+    }
+}
+
+impl Stream for MultipartStream {
+    type Item = Result<MultipartField, RipressError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.fields.poll_next(cx)
+    }
+}
+
+/// A field of a `MultipartStream`, its contents are a `Stream` of chunks.
+#[derive(Debug)]
+pub struct MultipartField {
+    name: String,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    data: BodyStream,
+}
+
+impl MultipartField {
+    /// Returns the name of the field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the file name sent by the client, `None` for text fields.
+    ///
+    /// The name is chosen by the client, don't use it as a path without checking it.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Returns the content type sent by the client, e.g. `image/png`.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Reads the rest of the field.
+    pub async fn bytes(&mut self) -> Result<Bytes, RipressError> {
+        let mut buffer = BytesMut::new();
+        while let Some(chunk) = self.next().await {
+            buffer.extend_from_slice(&chunk?);
+        }
+        Ok(buffer.freeze())
+    }
+
+    /// Reads the rest of the field as text.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(RipressError::InvalidBody)` if the field isn't valid UTF-8.
+    pub async fn text(&mut self) -> Result<String, RipressError> {
+        let bytes = self.bytes().await?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| {
+            RipressError::InvalidBody(format!("Field {} isn't valid UTF-8", self.name))
+        })
+    }
+}
+
+impl Stream for MultipartField {
+    type Item = Result<Bytes, RipressError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.data.poll_next_unpin(cx)
+    }
+}
+
+fn invalid_body(err: impl std::fmt::Display) -> RipressError {
+    RipressError::InvalidBody(format!("Invalid multipart body: {}", err))
+}
+
+fn create_temp_file(config: &MultipartConfig) -> std::io::Result<(TempPath, tokio::fs::File)> {
+    let temp_file = match &config.temp_dir {
+        Some(dir) => NamedTempFile::new_in(dir)?,
+        None => NamedTempFile::new()?,
+    };
+    let file = temp_file.reopen()?;
+    Ok((temp_file.into_temp_path(), tokio::fs::File::from_std(file)))
+}
+
+/// Reads a `multipart/form-data` body as it streams in, spooling large files to disk.
+///
+/// ## Returns
+///
+/// Returns `Err(RipressError::PayloadTooLarge)` if the body is larger than
+/// `limit`, a file is larger than `config.max_file_size` or a text field is
+/// larger than `config.max_field_size`, and `Err(RipressError::InvalidBody)`
+/// if the body is malformed.
+pub(crate) async fn read_multipart<S>(
+    headers: &HeaderMap,
+    payload: S,
+    limit: usize,
+    config: &MultipartConfig,
+) -> Result<Multipart, RipressError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+{
+    let mut parts = actix_multipart::Multipart::new(headers, payload);
+    let mut form = Multipart::default();
+    let mut total = 0;
+
+    while let Some(field) = parts.next().await {
+        let mut field = field.map_err(invalid_body)?;
+        let field_name = field.name().unwrap_or_default().to_string();
+        let file_name = field
+            .content_disposition()
+            .and_then(|disposition| disposition.get_filename())
+            .map(String::from);
+        let content_type = field.content_type().map(|mime| mime.to_string());
+
+        let mut buffer = BytesMut::new();
+        let mut spooled: Option<(TempPath, tokio::fs::File)> = None;
+        let mut size = 0;
+
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(invalid_body)?;
+            size += chunk.len();
+            total += chunk.len();
+            if total > limit {
+                return Err(RipressError::PayloadTooLarge(limit));
+            }
+            if file_name.is_some() && size > config.max_file_size {
+                return Err(RipressError::PayloadTooLarge(config.max_file_size));
+            }
+            if file_name.is_none() && size > config.max_field_size {
+                return Err(RipressError::PayloadTooLarge(config.max_field_size));
+            }
+
+            match &mut spooled {
+                Some((_, file)) => file.write_all(&chunk).await.map_err(RipressError::other)?,
+                None => {
+                    buffer.extend_from_slice(&chunk);
+                    if file_name.is_some() && buffer.len() > config.memory_threshold {
+                        let (path, mut file) =
+                            create_temp_file(config).map_err(RipressError::other)?;
+                        file.write_all(&buffer).await.map_err(RipressError::other)?;
+                        buffer.clear();
+                        spooled = Some((path, file));
+                    }
+                }
+            }
+        }
+
+        if file_name.is_none() {
+            let value = String::from_utf8(buffer.to_vec()).map_err(|_| {
+                RipressError::InvalidBody(format!("Field {} isn't valid UTF-8", field_name))
+            })?;
+            form.fields.push((field_name, value));
+            continue;
+        }
+
+        let data = match spooled {
+            Some((path, mut file)) => {
+                file.flush().await.map_err(RipressError::other)?;
+                FileData::Disk(Arc::new(path))
+            }
+            None => FileData::Memory(buffer.freeze()),
+        };
+
+        form.files.push(UploadedFile {
+            field_name,
+            file_name,
+            content_type,
+            size,
+            data,
+        });
+    }
+
+    Ok(form)
+}
+
+/// Sends the fields of a `multipart/form-data` body to a `MultipartStream` as they are read.
+///
+/// Errors are sent to the field being read, or to the stream of fields if
+/// the handler dropped it. Stops reading once the stream is dropped.
+pub(crate) async fn stream_multipart<S>(
+    headers: &HeaderMap,
+    payload: S,
+    limit: usize,
+    config: &MultipartConfig,
+    fields: StreamSender<MultipartField>,
+) where
+    S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+{
+    let mut parts = actix_multipart::Multipart::new(headers, payload);
+    let mut total = 0;
+
+    while let Some(field) = parts.next().await {
+        let mut field = match field {
+            Ok(field) => field,
+            Err(err) => {
+                fields.fail(invalid_body(err)).await;
+                return;
+            }
+        };
+        let file_name = field
+            .content_disposition()
+            .and_then(|disposition| disposition.get_filename())
+            .map(String::from);
+        let (sender, data) = stream_channel();
+        let streamed = MultipartField {
+            name: field.name().unwrap_or_default().to_string(),
+            file_name,
+            content_type: field.content_type().map(|mime| mime.to_string()),
+            data: chunk_stream(data),
+        };
+        let is_file = streamed.file_name.is_some();
+        if !fields.send(streamed).await {
+            return;
+        }
+
+        // Fields dropped by the handler are still read, to get to the next one
+        let mut data = Some(sender);
+        let mut size = 0;
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(invalid_body).and_then(|chunk| {
+                size += chunk.len();
+                total += chunk.len();
+                if total > limit {
+                    Err(RipressError::PayloadTooLarge(limit))
+                } else if is_file && size > config.max_file_size {
+                    Err(RipressError::PayloadTooLarge(config.max_file_size))
+                } else if !is_file && size > config.max_field_size {
+                    Err(RipressError::PayloadTooLarge(config.max_field_size))
+                } else {
+                    Ok(chunk)
+                }
+            });
+
+            match (chunk, &data) {
+                (Ok(chunk), Some(sender)) => {
+                    if !sender.send(chunk).await {
+                        data = None;
+                    }
+                }
+                (Ok(_), None) => {}
+                (Err(err), _) => {
+                    match data {
+                        Some(sender) => sender.fail(err).await,
+                        None => fields.fail(err).await,
+                    };
+                    return;
+                }
+            }
+        }
+
+        if let Some(sender) = data {
+            sender.finish();
+        }
+    }
+
+    fields.finish();
+}
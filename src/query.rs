@@ -0,0 +1,428 @@
+use crate::types::QueryError;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use std::{fmt::Display, str::FromStr};
+
+/// Parses a raw query string into its decoded key-value pairs, in order.
+pub(crate) fn parse_pairs(query: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect()
+}
+
+/// A query string parsed into a tree, following the bracket syntax.
+#[derive(Debug, PartialEq)]
+enum Node {
+    /// The values of a key, in order, e.g. `a` and `b` for `?tag=a&tag[]=b`.
+    Values(Vec<String>),
+    /// Nested keys, e.g. `status` for `?filter[status]=open`.
+    Map(Vec<(String, Node)>),
+}
+
+/// The deepest bracket nesting of a key, deeper brackets are kept as one segment.
+const MAX_KEY_DEPTH: usize = 16;
+
+/// Splits a key into its path, e.g. `filter[status]` into `filter` and `status`.
+///
+/// A trailing `[]` appends to the values of the key. Keys with unbalanced
+/// brackets are kept as they are, and the brackets past `MAX_KEY_DEPTH` are
+/// kept as the last segment, e.g. `[x][y]`.
+fn split_key(key: &str) -> Vec<&str> {
+    let Some(start) = key.find('[').filter(|start| *start > 0) else {
+        return vec![key];
+    };
+
+    let mut path = vec![&key[..start]];
+    let mut rest = &key[start..];
+
+    while !rest.is_empty() {
+        if path.len() > MAX_KEY_DEPTH {
+            path.push(rest);
+            return path;
+        }
+        let Some(segment) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) else {
+            return vec![key];
+        };
+        path.push(segment.0);
+        rest = segment.1;
+    }
+
+    if path.last() == Some(&"") {
+        path.pop();
+    }
+    path
+}
+
+/// Builds the tree of the query pairs.
+///
+/// ## Returns
+///
+/// Returns `Err(QueryError)` if a key is used both for a value and for nested
+/// keys, e.g. `?filter=open&filter[status]=open`.
+fn build_tree(pairs: &[(String, String)]) -> Result<Vec<(String, Node)>, QueryError> {
+    let mut root = Vec::new();
+
+    for (key, value) in pairs {
+        let path = split_key(key);
+        let (last, parents) = path.split_last().unwrap_or((&"", &[]));
+        let mut entries = &mut root;
+
+        for parent in parents {
+            let index = child_index(entries, parent, || Node::Map(Vec::new()));
+            entries = match &mut entries[index].1 {
+                Node::Map(children) => children,
+                Node::Values(_) => return Err(conflict(key)),
+            };
+        }
+
+        let index = child_index(entries, last, || Node::Values(Vec::new()));
+        match &mut entries[index].1 {
+            Node::Values(values) => values.push(value.clone()),
+            Node::Map(_) => return Err(conflict(key)),
+        }
+    }
+
+    Ok(root)
+}
+
+fn child_index(entries: &mut Vec<(String, Node)>, key: &str, new: impl Fn() -> Node) -> usize {
+    match entries.iter().position(|(existing, _)| existing == key) {
+        Some(index) => index,
+        None => {
+            entries.push((key.to_string(), new()));
+            entries.len() - 1
+        }
+    }
+}
+
+fn conflict(key: &str) -> QueryError {
+    QueryError::new(
+        Some(key.to_string()),
+        String::from("used both as a value and for nested keys"),
+    )
+}
+
+/// Deserializes query pairs into `T`.
+///
+/// Repeated keys are collected for sequences, e.g. `?tag=a&tag=b` into a
+/// `Vec<String>`, and scalars take the last value of their key. Bracketed keys
+/// are nested, e.g. `?filter[status]=open` into a `filter` struct or map, and
+/// `?items[0][name]=a` into a sequence of structs.
+pub(crate) fn from_query_pairs<T: DeserializeOwned>(
+    pairs: &[(String, String)],
+) -> Result<T, QueryError> {
+    let tree = build_tree(pairs)?;
+    T::deserialize(MapDeserializer { entries: &tree })
+}
+
+impl de::Error for QueryError {
+    fn custom<T: Display>(msg: T) -> Self {
+        QueryError::new(None, msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        QueryError::new(Some(field.to_string()), String::from("missing field"))
+    }
+}
+
+fn deserialize_node<'de, S: DeserializeSeed<'de>>(
+    seed: S,
+    key: &str,
+    node: &Node,
+) -> Result<S::Value, QueryError> {
+    let result = match node {
+        Node::Values(values) => seed.deserialize(ValueDeserializer { values }),
+        Node::Map(entries) => seed.deserialize(MapDeserializer { entries }),
+    };
+    result.map_err(|err| err.with_field(key))
+}
+
+/// Deserializes nested keys as a map, or as a sequence when they are indexes.
+struct MapDeserializer<'a> {
+    entries: &'a [(String, Node)],
+}
+
+impl<'de> de::Deserializer<'de> for MapDeserializer<'_> {
+    type Error = QueryError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_map(EntriesAccess {
+            entries: self.entries.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, QueryError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        let mut indexed = Vec::with_capacity(self.entries.len());
+        for (key, node) in self.entries {
+            let index: usize = key
+                .parse()
+                .map_err(|_| de::Error::custom(format!("expected an index, found {}", key)))?;
+            indexed.push((index, key.as_str(), node));
+        }
+        indexed.sort_by_key(|(index, _, _)| *index);
+
+        visitor.visit_seq(NodesAccess {
+            nodes: indexed.into_iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, QueryError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, QueryError> {
+        self.deserialize_seq(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct map struct enum identifier ignored_any
+    }
+}
+
+struct EntriesAccess<'a> {
+    entries: std::slice::Iter<'a, (String, Node)>,
+    value: Option<&'a (String, Node)>,
+}
+
+impl<'de> de::MapAccess<'de> for EntriesAccess<'_> {
+    type Error = QueryError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, QueryError> {
+        match self.entries.next() {
+            Some(entry) => {
+                self.value = Some(entry);
+                seed.deserialize(entry.0.as_str().into_deserializer())
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, QueryError> {
+        let (key, node) = self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before key"))?;
+
+        deserialize_node(seed, key, node)
+    }
+}
+
+struct NodesAccess<'a, I: Iterator<Item = (usize, &'a str, &'a Node)>> {
+    nodes: I,
+}
+
+impl<'a, 'de, I> de::SeqAccess<'de> for NodesAccess<'a, I>
+where
+    I: Iterator<Item = (usize, &'a str, &'a Node)>,
+{
+    type Error = QueryError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, QueryError> {
+        match self.nodes.next() {
+            Some((_, key, node)) => deserialize_node(seed, key, node).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Deserializes the values of a single key.
+struct ValueDeserializer<'a> {
+    values: &'a [String],
+}
+
+impl ValueDeserializer<'_> {
+    fn last(&self) -> &str {
+        self.values.last().map_or("", String::as_str)
+    }
+
+    fn parse<T>(&self) -> Result<T, QueryError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.last().parse().map_err(de::Error::custom)
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = QueryError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        if self.values.len() > 1 {
+            self.deserialize_seq(visitor)
+        } else {
+            visitor.visit_str(self.last())
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_str(self.last())
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_str(self.last())
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_bytes(self.last().as_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_bytes(self.last().as_bytes())
+    }
+
+    /// An empty value, e.g. `?page=`, is treated as missing.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        if self.last().is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, QueryError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, QueryError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_seq(ValuesAccess {
+            values: self.values.iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, QueryError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, QueryError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, QueryError> {
+        let value: de::value::StrDeserializer<QueryError> = self.last().into_deserializer();
+        de::Deserializer::deserialize_enum(value, name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        map struct identifier
+    }
+}
+
+struct ValuesAccess<'a> {
+    values: std::slice::Iter<'a, String>,
+}
+
+impl<'de> de::SeqAccess<'de> for ValuesAccess<'_> {
+    type Error = QueryError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, QueryError> {
+        match self.values.next() {
+            Some(value) => seed
+                .deserialize(ValueDeserializer {
+                    values: std::slice::from_ref(value),
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
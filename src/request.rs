@@ -1,13 +1,30 @@
-use crate::types::{HttpMethods, HttpRequestError, RequestBodyContent, RequestBodyType};
-use actix_web::{http::Method, HttpMessage};
+use crate::body_stream::{body_stream, PendingBody};
+use crate::multipart::read_multipart;
+use crate::query::{from_query_pairs, parse_pairs};
+use crate::types::{
+    BodyStream, Bytes, Headers, HttpMethods, HttpRequestError, MediaType, Multipart,
+    MultipartConfig, MultipartStream, QueryError, RequestBodyContent, RequestBodyType,
+    RipressError, StateMap, UploadedFile,
+};
+use actix_web::http::{header::CONTENT_TYPE, Method};
+use encoding_rs::{Encoding, UTF_8};
 use futures_util::stream::StreamExt;
-use std::collections::HashMap;
-use url;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+/// The maximum request body size used when none is configured, in bytes.
+pub(crate) const DEFAULT_BODY_LIMIT: usize = 262_144;
 
 #[derive(Debug, Clone)]
 struct RequestBody {
     content: RequestBodyContent,
     content_type: RequestBodyType,
+    media_type: Option<MediaType>,
+    raw: Bytes,
 }
 
 // Represents an incoming HTTP request with comprehensive access to request data.
@@ -16,6 +33,9 @@ struct RequestBody {
 /// of an HTTP request including headers, cookies, query parameters, route parameters,
 /// and request body content.
 ///
+/// The immutable parts of the request (headers, cookies, queries, params and
+/// body) are shared behind an `Arc`, so cloning a request is cheap.
+///
 /// ## Examples
 ///
 /// Basic usage:
@@ -48,13 +68,16 @@ struct RequestBody {
 #[derive(Debug, Clone)]
 pub struct HttpRequest {
     /// Dynamic route parameters extracted from the URL.
-    params: HashMap<String, String>,
+    params: Arc<HashMap<String, String>>,
+
+    /// Query parameters from the request URL, in order.
+    queries: Arc<Vec<(String, String)>>,
 
-    /// Query parameters from the request URL.
-    queries: HashMap<String, String>,
+    /// The raw query string, without the leading `?`.
+    query_string: Arc<str>,
 
     /// The request body, which may contain JSON, text, or form data.
-    body: RequestBody,
+    body: Arc<RequestBody>,
 
     /// The IP address of the client making the request.
     ip: String,
@@ -69,14 +92,23 @@ pub struct HttpRequest {
     path: String,
 
     /// The request's headers
-    headers: HashMap<String, String>,
+    headers: Arc<Headers>,
 
     /// The request's cookies
-    cookies: HashMap<String, String>,
+    cookies: Arc<HashMap<String, String>>,
 
     /// Protocol of the request (HTTP or HTTPs)
     protocol: String,
 
+    /// The pattern of the route that matched the request, e.g. `/user/{id}`.
+    route_pattern: Option<Arc<str>>,
+
+    /// The application state added with `App::with_state`.
+    state: Arc<StateMap>,
+
+    /// The unread body of a streaming route, until the handler takes it.
+    body_stream: Option<Arc<Mutex<Option<PendingBody>>>>,
+
     data: HashMap<String, String>,
 }
 
@@ -97,19 +129,25 @@ impl HttpRequest {
     /// ```
     pub fn new() -> Self {
         Self {
-            params: HashMap::new(),
-            queries: HashMap::new(),
-            body: RequestBody {
+            params: Arc::new(HashMap::new()),
+            queries: Arc::new(Vec::new()),
+            query_string: Arc::from(""),
+            body: Arc::new(RequestBody {
                 content_type: RequestBodyType::TEXT,
                 content: RequestBodyContent::TEXT(String::new()),
-            },
+                media_type: None,
+                raw: Bytes::new(),
+            }),
             ip: String::new(),
             method: HttpMethods::GET,
             origin_url: String::new(),
             path: String::new(),
-            headers: HashMap::new(),
-            cookies: HashMap::new(),
+            headers: Arc::new(Headers::new()),
+            cookies: Arc::new(HashMap::new()),
             protocol: String::from("http"),
+            route_pattern: None,
+            state: Arc::new(HashMap::new()),
+            body_stream: None,
             data: HashMap::new(),
         }
     }
@@ -281,6 +319,23 @@ impl HttpRequest {
         }
     }
 
+    /// Returns the pattern of the route that matched the request.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Some(&str)` with the pattern the route was registered with, e.g.
+    /// `/user/{id}`, or `None` if the request was not routed.
+    ///
+    /// ## Example
+    /// ```
+    /// let req = ripress::context::HttpRequest::new();
+    /// let pattern = req.route_pattern();
+    /// println!("Pattern: {:?}", pattern);
+    /// ```
+    pub fn route_pattern(&self) -> Option<&str> {
+        self.route_pattern.as_deref()
+    }
+
     /// Adds data from the middleware into the request.
     ///
     /// ## Arguments
@@ -321,8 +376,32 @@ impl HttpRequest {
         self.data.get(key)
     }
 
+    /// Returns the application state of type `T` added with `App::with_state`.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use ripress::context::HttpRequest;
+    ///
+    /// struct Config {
+    ///     name: String,
+    /// }
+    ///
+    /// let req = HttpRequest::new();
+    /// if let Some(config) = req.get_state::<Config>() {
+    ///     println!("App name: {}", config.name);
+    /// }
+    /// ```
+    pub fn get_state<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.state
+            .get(&std::any::TypeId::of::<T>())
+            .and_then(|state| state.downcast_ref::<T>())
+    }
+
     /// Returns header based on the key.
     ///
+    /// The lookup is case-insensitive. If the header was sent several times,
+    /// the first value is returned.
+    ///
     /// ## Arguments
     ///
     /// * `header_name` - The name of the header to retrieve
@@ -341,15 +420,49 @@ impl HttpRequest {
     /// ```
 
     pub fn get_header(&self, header_name: &str) -> Result<&str, HttpRequestError> {
-        let header_name = header_name.to_lowercase();
-        let header = self.headers.get(&header_name);
-
-        match header {
+        match self.headers.get(header_name) {
             Some(header_str) => Ok(header_str),
-            None => Err(HttpRequestError::MissingHeader(header_name)),
+            None => Err(HttpRequestError::MissingHeader(header_name.to_lowercase())),
         }
     }
 
+    /// Returns the `Last-Event-ID` header, sent by `EventSource` clients when they reconnect.
+    ///
+    /// Use it to resume a Server-Sent Events stream after the last event the
+    /// client received.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Some(&str)` with the id of the last event, or `None` on the first connection.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// let req = ripress::context::HttpRequest::new();
+    /// let last_id: u64 = req.last_event_id().and_then(|id| id.parse().ok()).unwrap_or(0);
+    /// println!("Resuming after event {}", last_id);
+    /// ```
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.headers.get("last-event-id")
+    }
+
+    /// Returns all the request's headers.
+    ///
+    /// Use it to read headers sent several times, or with values that aren't
+    /// valid text.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// let req = ripress::context::HttpRequest::new();
+    /// for forwarded in req.headers().get_all("x-forwarded-for") {
+    ///     println!("Forwarded for: {}", forwarded);
+    /// }
+    /// ```
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
     /// Returns query parameters.
     ///
     /// ## Arguments
@@ -369,7 +482,12 @@ impl HttpRequest {
     /// ```
 
     pub fn get_query(&self, query_name: &str) -> Result<&str, HttpRequestError> {
-        let query = self.queries.get(query_name).map(|v| v);
+        let query = self
+            .queries
+            .iter()
+            .rev()
+            .find(|(key, _)| key == query_name)
+            .map(|(_, value)| value.as_str());
 
         match query {
             Some(query_str) => Ok(query_str),
@@ -377,6 +495,77 @@ impl HttpRequest {
         }
     }
 
+    /// Returns every value of a query parameter, in order.
+    ///
+    /// Values sent with the bracket syntax, e.g. `tag[]=a`, are included.
+    ///
+    /// ## Arguments
+    ///
+    /// * `query_name` - The name of the query parameter to retrieve
+    ///
+    /// ## Example
+    /// ```
+    /// let req = ripress::context::HttpRequest::new();
+    /// // For /posts?tag=rust&tag=web
+    /// let tags = req.get_query_all("tag");
+    /// println!("Tags: {:?}", tags);
+    /// ```
+    pub fn get_query_all(&self, query_name: &str) -> Vec<&str> {
+        self.queries
+            .iter()
+            .filter(|(key, _)| {
+                key == query_name || key.strip_suffix("[]").is_some_and(|key| key == query_name)
+            })
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Returns the raw query string, without the leading `?`.
+    ///
+    /// ## Example
+    /// ```
+    /// let req = ripress::context::HttpRequest::new();
+    /// assert_eq!(req.query_string(), "");
+    /// ```
+    pub fn query_string(&self) -> &str {
+        &self.query_string
+    }
+
+    /// Deserializes the query string into `T`.
+    ///
+    /// Values are parsed into the field types, missing `Option` fields and
+    /// empty values become `None`, `#[serde(default)]` fields fall back to their
+    /// default and repeated keys, e.g. `?tag=a&tag=b`, fill sequences.
+    ///
+    /// Bracketed keys are nested, e.g. `?filter[status]=open` fills a `filter`
+    /// struct or map and `?items[0][id]=1&items[1][id]=2` a sequence of structs.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(QueryError)` naming the failing field if the query doesn't match `T`.
+    ///
+    /// ## Example
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// ##[derive(Deserialize)]
+    /// struct Search {
+    ///     q: String,
+    ///     page: Option<u32>,
+    ///     #[serde(default)]
+    ///     tag: Vec<String>,
+    /// }
+    ///
+    /// let req = ripress::context::HttpRequest::new();
+    /// match req.query::<Search>() {
+    ///     Ok(search) => println!("Searching {} on page {:?}", search.q, search.page),
+    ///     Err(e) => println!("Invalid {:?}: {}", e.field(), e.message()),
+    /// }
+    /// ```
+    pub fn query<T: serde::de::DeserializeOwned>(&self) -> Result<T, QueryError> {
+        from_query_pairs(&self.queries)
+    }
+
     /// Returns the protocol on which the request was made (http or https)
     ///
     /// ## Returns
@@ -498,13 +687,22 @@ impl HttpRequest {
     /// println!("key2 = : {:?}", form_data.get("key2"));
     /// ```
     ///
-    /// This function returns a HashMap of the form data.
+    /// This function returns a HashMap of the form data, or of the text fields of a multipart body.
     /// Returns an `Result<HashMap<String, String>>`, where `Ok(HashMap<String, String>)` contains the form_data if it is valid form data, or `Err(error)` if it is not.
 
     pub fn form_data(&self) -> Result<HashMap<String, String>, String> {
         let mut form_data: HashMap<String, String> = HashMap::new();
         let body = &self.body;
 
+        if let RequestBodyContent::MULTIPART(form) = &body.content {
+            for (name, value) in form.fields() {
+                form_data
+                    .entry(name.to_string())
+                    .or_insert_with(|| value.to_string());
+            }
+            return Ok(form_data);
+        }
+
         if body.content_type == RequestBodyType::FORM {
             if let RequestBodyContent::FORM(ref text_value) = body.content {
                 serde_urlencoded::from_str::<HashMap<String, String>>(text_value)
@@ -522,26 +720,197 @@ impl HttpRequest {
         }
     }
 
+    /// Takes the body of a streaming route, to read it chunk by chunk.
+    ///
+    /// Routes stream their body when added with `Route::stream_body`, their
+    /// body isn't read before the handler runs and `text`, `json`, `bytes`...
+    /// are empty.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `None` if the route doesn't stream its body, or if the stream
+    /// was already taken, e.g. by a middleware.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use futures_util::StreamExt;
+    /// use ripress::context::{HttpRequest, HttpResponse};
+    ///
+    /// async fn upload(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+    ///     let mut body = match req.body_stream() {
+    ///         Some(body) => body,
+    ///         None => return res.bad_request().text("Expected a streamed body"),
+    ///     };
+    ///
+    ///     let mut size = 0;
+    ///     while let Some(chunk) = body.next().await {
+    ///         match chunk {
+    ///             Ok(chunk) => size += chunk.len(),
+    ///             Err(err) => return res.error(err),
+    ///         }
+    ///     }
+    ///     res.ok().text(format!("Received {} bytes", size))
+    /// }
+    /// ```
+    pub fn body_stream(&self) -> Option<BodyStream> {
+        self.take_pending_body().map(PendingBody::into_stream)
+    }
+
+    /// Takes the `multipart/form-data` body of a streaming route, to read it field by field.
+    ///
+    /// Unlike `multipart`, fields are received while the body streams in, so
+    /// the handler can process or reject them before the whole body is read.
+    /// See `MultipartStream`.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `None` if the route doesn't stream its body, if the body was
+    /// already taken, or if the request isn't `multipart/form-data`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::context::{HttpRequest, HttpResponse};
+    ///
+    /// async fn upload(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+    ///     let mut form = match req.multipart_stream() {
+    ///         Some(form) => form,
+    ///         None => return res.bad_request().text("Expected a multipart body"),
+    ///     };
+    ///
+    ///     let mut names = Vec::new();
+    ///     while let Some(field) = form.next_field().await {
+    ///         match field {
+    ///             Ok(field) => names.push(field.name().to_string()),
+    ///             Err(err) => return res.error(err),
+    ///         }
+    ///     }
+    ///     res.ok().text(names.join(", "))
+    /// }
+    /// ```
+    pub fn multipart_stream(&self) -> Option<MultipartStream> {
+        if self.body_type() != RequestBodyType::MULTIPART {
+            return None;
+        }
+        self.take_pending_body().map(PendingBody::into_multipart)
+    }
+
+    fn take_pending_body(&self) -> Option<PendingBody> {
+        self.body_stream
+            .as_ref()
+            .and_then(|body| body.lock().ok()?.take())
+    }
+
+    /// Returns the parsed `Content-Type` of the request.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `None` if the request has no valid `Content-Type` header.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::context::HttpRequest;
+    ///
+    /// let req = HttpRequest::new();
+    /// if let Some(media_type) = req.media_type() {
+    ///     println!("{} in {:?}", media_type.essence(), media_type.charset());
+    /// }
+    /// ```
+    pub fn media_type(&self) -> Option<&MediaType> {
+        self.body.media_type.as_ref()
+    }
+
+    /// Returns the parsed `multipart/form-data` body.
+    ///
+    /// The whole body is read before the handler runs, use `multipart_stream`
+    /// on a streaming route to handle the fields as they arrive.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(&Multipart)` with the text fields and files of the body, or
+    /// `Err(String)` if the request isn't `multipart/form-data`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::context::HttpRequest;
+    ///
+    /// let req = HttpRequest::new();
+    /// match req.multipart() {
+    ///     Ok(form) => println!("Title: {:?}", form.field("title")),
+    ///     Err(e) => println!("Error: {}", e),
+    /// }
+    /// ```
+    pub fn multipart(&self) -> Result<&Multipart, String> {
+        match &self.body.content {
+            RequestBodyContent::MULTIPART(form) => Ok(form),
+            _ => Err(String::from("Wrong body type")),
+        }
+    }
+
+    /// Returns the files uploaded in a `multipart/form-data` body.
+    ///
+    /// Returns an empty slice for other content types.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::context::HttpRequest;
+    ///
+    /// let req = HttpRequest::new();
+    /// for file in req.files() {
+    ///     println!("{:?}: {} bytes", file.file_name(), file.size());
+    /// }
+    /// ```
+    pub fn files(&self) -> &[UploadedFile] {
+        match &self.body.content {
+            RequestBodyContent::MULTIPART(form) => form.files(),
+            _ => &[],
+        }
+    }
+
+    /// Returns the raw bytes of the request body.
+    ///
+    /// Empty for `multipart/form-data` bodies, which are parsed as they are
+    /// read. Available for every other content type, and kept as sent even when the body
+    /// was parsed as JSON or form data, e.g. to verify a signature of the body.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::context::HttpRequest;
+    ///
+    /// let req = HttpRequest::new();
+    /// let body: &[u8] = req.bytes();
+    /// println!("Received {} bytes", body.len());
+    /// ```
+    pub fn bytes(&self) -> &Bytes {
+        &self.body.raw
+    }
+
     pub async fn from_actix_request(
         req: actix_web::HttpRequest,
-        mut payload: actix_web::web::Payload,
-    ) -> Result<Self, actix_web::Error> {
+        payload: actix_web::web::Payload,
+    ) -> Result<Self, RipressError> {
+        let mut our_req = HttpRequest::from_actix_parts(&req);
+        our_req
+            .read_body(payload, DEFAULT_BODY_LIMIT, &MultipartConfig::default())
+            .await?;
+        Ok(our_req)
+    }
+
+    /// Builds a request from everything but the body of an actix request.
+    pub(crate) fn from_actix_parts(req: &actix_web::HttpRequest) -> Self {
         // Extract all necessary data from the request early
         let query_string = req.query_string();
 
-        let queries = url::form_urlencoded::parse(query_string.as_bytes())
-            .filter_map(|(key, value)| Some((key.to_string(), value.to_string())))
-            .collect::<HashMap<String, String>>();
+        let queries = parse_pairs(query_string);
 
-        let ip = get_real_ip(&req);
+        let ip = get_real_ip(req);
 
-        let method = match req.method() {
-            &Method::GET => HttpMethods::GET,
-            &Method::POST => HttpMethods::POST,
-            &Method::PUT => HttpMethods::PUT,
-            &Method::DELETE => HttpMethods::DELETE,
-            _ => HttpMethods::GET,
-        };
+        let method = to_http_method(req.method()).unwrap_or(HttpMethods::GET);
 
         let origin_url = req.uri().to_string();
         let path = req.path().to_string();
@@ -555,11 +924,7 @@ impl HttpRequest {
             }
         });
 
-        let mut headers: HashMap<String, String> = HashMap::new();
-
-        req.headers().iter().for_each(|(key, value)| {
-            headers.insert(key.to_string(), value.to_str().unwrap().to_string());
-        });
+        let headers = req.headers().clone();
 
         let params: HashMap<String, String> = req
             .match_info()
@@ -567,82 +932,195 @@ impl HttpRequest {
             .map(|(k, v)| (k.to_string(), v.to_string()))
             .collect();
 
-        let content_type = determine_content_type(req.content_type());
+        let content_type_header = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        let media_type = MediaType::parse(content_type_header);
+        let content_type = body_type_of(media_type.as_ref());
         let protocol = req.connection_info().scheme().to_string();
 
+        HttpRequest {
+            params: Arc::new(params),
+            queries: Arc::new(queries),
+            query_string: Arc::from(query_string),
+            body: Arc::new(RequestBody {
+                content: RequestBodyContent::TEXT(String::new()),
+                content_type,
+                media_type,
+                raw: Bytes::new(),
+            }),
+            ip,
+            method,
+            origin_url,
+            path,
+            headers: Arc::new(Headers::from(headers)),
+            cookies: Arc::new(cookies),
+            protocol,
+            route_pattern: None,
+            state: Arc::new(HashMap::new()),
+            body_stream: None,
+            data: HashMap::new(),
+        }
+    }
+
+    /// Reads the payload and parses it according to the request's content type.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(RipressError::PayloadTooLarge)` if the body is larger than
+    /// `limit` bytes, or `Err(RipressError::InvalidBody)` if it cannot be parsed.
+    ///
+    /// Text, JSON and form bodies are decoded from the `charset` of their
+    /// content type, unsupported charsets fail with 415 Unsupported Media Type.
+    /// Text bodies that aren't valid are kept as binary bodies, and multipart
+    /// bodies are read with `multipart`, without keeping the raw body.
+    pub(crate) async fn read_body(
+        &mut self,
+        mut payload: actix_web::web::Payload,
+        limit: usize,
+        multipart: &MultipartConfig,
+    ) -> Result<(), RipressError> {
+        let mut content_type = self.body.content_type;
+        let media_type = self.body.media_type.clone();
+
+        self.check_content_length(limit)?;
+
+        if content_type == RequestBodyType::MULTIPART {
+            let form =
+                read_multipart(self.headers.as_header_map(), payload, limit, multipart).await?;
+            self.body = Arc::new(RequestBody {
+                content: RequestBodyContent::MULTIPART(form),
+                content_type,
+                media_type,
+                raw: Bytes::new(),
+            });
+            return Ok(());
+        }
+
         // Read the body
         let mut body = actix_web::web::BytesMut::new();
         while let Some(chunk) = payload.next().await {
-            let chunk = chunk?;
-            if (body.len() + chunk.len()) > 262_144 {
-                return Err(actix_web::error::ErrorBadRequest("Body too large"));
+            let chunk = chunk.map_err(|e| RipressError::InvalidBody(e.to_string()))?;
+            if (body.len() + chunk.len()) > limit {
+                return Err(RipressError::PayloadTooLarge(limit));
             }
             body.extend_from_slice(&chunk);
         }
 
-        let request_body = match content_type {
-            RequestBodyType::FORM => {
-                let body_string = match std::str::from_utf8(&body) {
-                    Ok(s) => s.to_string(),
-                    Err(_) => {
-                        return Err(actix_web::error::ErrorBadRequest("Invalid UTF-8 sequence"));
-                    }
-                };
+        let raw = body.freeze();
+        let encoding = match content_type {
+            RequestBodyType::TEXT | RequestBodyType::JSON | RequestBodyType::FORM => {
+                charset_encoding(media_type.as_ref())?
+            }
+            RequestBodyType::BINARY | RequestBodyType::MULTIPART => None,
+        };
 
-                RequestBody {
-                    content: RequestBodyContent::FORM(body_string),
-                    content_type: RequestBodyType::FORM,
+        let content = match content_type {
+            RequestBodyType::FORM => match decode_text(&raw, encoding) {
+                Some(text) => RequestBodyContent::FORM(text),
+                None => {
+                    return Err(RipressError::InvalidBody(String::from(
+                        "Invalid character encoding",
+                    )));
                 }
-            }
+            },
             RequestBodyType::JSON => {
-                let body_json = match std::str::from_utf8(&body) {
-                    Ok(s) => match serde_json::from_str(s) {
-                        Ok(json) => json,
-                        Err(e) => {
-                            return Err(actix_web::error::ErrorBadRequest(format!(
-                                "Invalid JSON: {}",
-                                e
+                let json = match encoding {
+                    Some(_) => match decode_text(&raw, encoding) {
+                        Some(text) => serde_json::from_str(&text),
+                        None => {
+                            return Err(RipressError::InvalidBody(String::from(
+                                "Invalid character encoding",
                             )));
                         }
                     },
-                    Err(_) => {
-                        return Err(actix_web::error::ErrorBadRequest("Invalid UTF-8 sequence"));
-                    }
+                    None => serde_json::from_slice(&raw),
                 };
-
-                RequestBody {
-                    content: RequestBodyContent::JSON(body_json),
-                    content_type: RequestBodyType::JSON,
+                match json {
+                    Ok(json) => RequestBodyContent::JSON(json),
+                    Err(e) => {
+                        return Err(RipressError::InvalidBody(format!("Invalid JSON: {}", e)));
+                    }
                 }
             }
-            RequestBodyType::TEXT => {
-                let body_string = match std::str::from_utf8(&body) {
-                    Ok(s) => s.to_string(),
-                    Err(_) => {
-                        return Err(actix_web::error::ErrorBadRequest("Invalid UTF-8 sequence"));
-                    }
-                };
-
-                RequestBody {
-                    content: RequestBodyContent::TEXT(body_string),
-                    content_type: RequestBodyType::TEXT,
+            RequestBodyType::TEXT => match decode_text(&raw, encoding) {
+                Some(text) => RequestBodyContent::TEXT(text),
+                None => {
+                    content_type = RequestBodyType::BINARY;
+                    RequestBodyContent::BINARY(raw.clone())
                 }
+            },
+            RequestBodyType::BINARY | RequestBodyType::MULTIPART => {
+                RequestBodyContent::BINARY(raw.clone())
             }
         };
 
-        Ok(HttpRequest {
-            params,
-            queries,
-            body: request_body,
-            ip,
-            method,
-            origin_url,
-            path,
-            headers,
-            cookies,
-            protocol,
-            data: HashMap::new(),
-        })
+        self.body = Arc::new(RequestBody {
+            content,
+            content_type,
+            media_type,
+            raw,
+        });
+        Ok(())
+    }
+
+    /// Makes the body available through `body_stream` and `multipart_stream` instead of reading it.
+    ///
+    /// ## Returns
+    ///
+    /// Returns the future reading the payload into the stream, to be polled
+    /// alongside the handler, or `Err(RipressError::PayloadTooLarge)` if the
+    /// announced body is larger than `limit` bytes.
+    pub(crate) fn stream_body(
+        &mut self,
+        payload: actix_web::web::Payload,
+        limit: usize,
+        multipart: &MultipartConfig,
+    ) -> Result<impl Future<Output = ()>, RipressError> {
+        self.check_content_length(limit)?;
+
+        let headers = self.headers.as_header_map().clone();
+        let (body, forward) = body_stream(payload, limit, headers, multipart);
+        self.body_stream = Some(Arc::new(Mutex::new(Some(body))));
+        Ok(forward)
+    }
+
+    /// Rejects bodies announcing a `Content-Length` larger than `limit`, before reading them.
+    fn check_content_length(&self, limit: usize) -> Result<(), RipressError> {
+        let content_length = self
+            .headers
+            .get("content-length")
+            .and_then(|length| length.parse::<usize>().ok());
+        if content_length.is_some_and(|length| length > limit) {
+            return Err(RipressError::PayloadTooLarge(limit));
+        }
+        Ok(())
+    }
+
+    /// Sets the pattern of the matched route and the parameters captured from the path.
+    pub(crate) fn set_route(&mut self, pattern: &str, params: HashMap<String, String>) {
+        self.route_pattern = Some(Arc::from(pattern));
+        self.params = Arc::new(params);
+    }
+
+    /// Returns the type of the request body, from its content type.
+    pub(crate) fn body_type(&self) -> RequestBodyType {
+        self.body.content_type
+    }
+
+    /// Sets the application state the request can access.
+    pub(crate) fn set_state(&mut self, state: Arc<StateMap>) {
+        self.state = state;
+    }
+
+    pub(crate) fn body_content(&self) -> &RequestBodyContent {
+        &self.body.content
+    }
+
+    pub(crate) fn params_map(&self) -> &HashMap<String, String> {
+        &self.params
     }
 }
 
@@ -655,17 +1133,105 @@ impl HttpRequest {
 /// ## Returns
 ///
 /// Returns the appropriate `RequestBodyType` enum variant.
-
+#[cfg(test)]
 pub(crate) fn determine_content_type(content_type: &str) -> RequestBodyType {
-    if content_type == "application/json" {
-        return RequestBodyType::JSON;
-    } else if content_type == "application/x-www-form-urlencoded" {
-        return RequestBodyType::FORM;
+    body_type_of(MediaType::parse(content_type).as_ref())
+}
+
+/// Determines the body type from a parsed content type, `TEXT` if there is none.
+fn body_type_of(media_type: Option<&MediaType>) -> RequestBodyType {
+    let media_type = match media_type {
+        Some(media_type) => media_type,
+        None => return RequestBodyType::TEXT,
+    };
+
+    if media_type.is_json() {
+        RequestBodyType::JSON
+    } else if media_type.essence() == "application/x-www-form-urlencoded" {
+        RequestBodyType::FORM
+    } else if media_type.essence() == "multipart/form-data" {
+        RequestBodyType::MULTIPART
+    } else if is_binary_content_type(media_type.essence()) {
+        RequestBodyType::BINARY
     } else {
         RequestBodyType::TEXT
     }
 }
 
+/// Returns the encoding of a content type's charset, or `None` for UTF-8 and
+/// content types without a charset.
+///
+/// ## Returns
+///
+/// Returns `Err(RipressError::Status)` with 415 for unknown charsets.
+fn charset_encoding(
+    media_type: Option<&MediaType>,
+) -> Result<Option<&'static Encoding>, RipressError> {
+    let charset = match media_type.and_then(MediaType::charset) {
+        Some(charset) => charset,
+        None => return Ok(None),
+    };
+
+    match Encoding::for_label(charset.as_bytes()) {
+        Some(encoding) if encoding == UTF_8 => Ok(None),
+        Some(encoding) => Ok(Some(encoding)),
+        None => Err(RipressError::Status(
+            415,
+            format!("Unsupported charset {}", charset),
+        )),
+    }
+}
+
+/// Decodes a body from the encoding, or from UTF-8 if `None`.
+///
+/// ## Returns
+///
+/// Returns `None` if the body isn't valid in the encoding.
+fn decode_text(raw: &[u8], encoding: Option<&'static Encoding>) -> Option<String> {
+    match encoding {
+        Some(encoding) => encoding
+            .decode_without_bom_handling_and_without_replacement(raw)
+            .map(Cow::into_owned),
+        None => std::str::from_utf8(raw).ok().map(String::from),
+    }
+}
+
+/// Returns `true` for content types of bodies that aren't text, e.g. images or archives.
+fn is_binary_content_type(content_type: &str) -> bool {
+    const BINARY_PREFIXES: [&str; 4] = ["image/", "audio/", "video/", "font/"];
+    const BINARY_TYPES: [&str; 8] = [
+        "application/octet-stream",
+        "application/protobuf",
+        "application/x-protobuf",
+        "application/gzip",
+        "application/zip",
+        "application/pdf",
+        "application/wasm",
+        "application/msgpack",
+    ];
+
+    BINARY_PREFIXES
+        .iter()
+        .any(|prefix| content_type.starts_with(prefix))
+        || BINARY_TYPES.contains(&content_type)
+}
+
+/// Maps an actix request method to the methods ripress can route.
+///
+/// ## Returns
+///
+/// Returns `None` for methods that ripress has no routes for.
+pub(crate) fn to_http_method(method: &Method) -> Option<HttpMethods> {
+    match *method {
+        Method::GET => Some(HttpMethods::GET),
+        Method::POST => Some(HttpMethods::POST),
+        Method::PUT => Some(HttpMethods::PUT),
+        Method::DELETE => Some(HttpMethods::DELETE),
+        Method::PATCH => Some(HttpMethods::PATCH),
+        _ => None,
+    }
+}
+
 pub(crate) fn get_real_ip(req: &actix_web::HttpRequest) -> String {
     req.headers()
         .get("X-Forwarded-For")
@@ -681,50 +1247,74 @@ pub(crate) fn get_real_ip(req: &actix_web::HttpRequest) -> String {
 #[cfg(test)]
 impl HttpRequest {
     pub(crate) fn set_query(&mut self, key: &str, value: &str) {
-        self.queries.insert(key.to_string(), value.to_string());
+        Arc::make_mut(&mut self.queries).push((key.to_string(), value.to_string()));
+
+        let pair = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(key, value)
+            .finish();
+        self.query_string = if self.query_string.is_empty() {
+            Arc::from(pair)
+        } else {
+            Arc::from(format!("{}&{}", self.query_string, pair))
+        };
     }
 
     pub(crate) fn set_header(&mut self, key: &str, value: &str) {
-        self.headers.insert(key.to_string(), value.to_string());
+        Arc::make_mut(&mut self.headers).insert(key, value).unwrap();
     }
 
     pub(crate) fn set_cookie(&mut self, key: &str, value: &str) {
-        self.cookies.insert(key.to_string(), value.to_string());
+        Arc::make_mut(&mut self.cookies).insert(key.to_string(), value.to_string());
     }
 
     pub(crate) fn set_param(&mut self, key: &str, value: &str) {
-        self.params.insert(key.to_string(), value.to_string());
+        Arc::make_mut(&mut self.params).insert(key.to_string(), value.to_string());
     }
 
     pub(crate) fn set_json<J>(&mut self, json: J, content_type: RequestBodyType)
     where
         J: serde::de::DeserializeOwned + serde::Serialize,
     {
-        self.body.content_type = content_type;
-        self.body.content = RequestBodyContent::JSON(serde_json::to_value(json).unwrap());
+        let body = Arc::make_mut(&mut self.body);
+        body.content_type = content_type;
+        body.raw = Bytes::from(serde_json::to_vec(&json).unwrap());
+        body.content = RequestBodyContent::JSON(serde_json::to_value(json).unwrap());
     }
 
     pub(crate) fn set_text(&mut self, text: &str, content_type: RequestBodyType) {
-        self.body.content_type = content_type;
-        self.body.content = RequestBodyContent::TEXT(text.to_string());
+        let body = Arc::make_mut(&mut self.body);
+        body.content_type = content_type;
+        body.content = RequestBodyContent::TEXT(text.to_string());
+        body.raw = Bytes::copy_from_slice(text.as_bytes());
+    }
+
+    pub(crate) fn set_bytes(&mut self, bytes: &[u8], content_type: RequestBodyType) {
+        let body = Arc::make_mut(&mut self.body);
+        body.content_type = content_type;
+        body.raw = Bytes::copy_from_slice(bytes);
+        body.content = RequestBodyContent::BINARY(body.raw.clone());
     }
 
     pub(crate) fn set_form(&mut self, key: &str, value: &str, content_type: RequestBodyType) {
-        self.body.content_type = content_type;
+        let body = Arc::make_mut(&mut self.body);
+        body.content_type = content_type;
 
-        match &mut self.body.content {
+        match &mut body.content {
             RequestBodyContent::FORM(existing) => {
                 existing.push('&');
                 existing.push_str(&format!("{key}={value}"));
             }
             _ => {
-                self.body.content = RequestBodyContent::FORM(format!("{key}={value}"));
+                body.content = RequestBodyContent::FORM(format!("{key}={value}"));
             }
         }
+        if let RequestBodyContent::FORM(text) = &body.content {
+            body.raw = Bytes::copy_from_slice(text.as_bytes());
+        }
     }
 
     pub(crate) fn set_content_type(&mut self, content_type: RequestBodyType) {
-        self.body.content_type = content_type;
+        Arc::make_mut(&mut self.body).content_type = content_type;
     }
 
     pub(crate) fn set_method(&mut self, method: HttpMethods) {
@@ -1,9 +1,28 @@
-use crate::types::{HttpResponseError, ResponseContentBody, ResponseContentType};
+use crate::{
+    sse::{Event, Sse},
+    types::{
+        BoxError, Bytes, Headers, HttpResponseError, IntoResponse, ResponseContentBody,
+        ResponseContentType, ResponseFile, ResponseStream, RipressError,
+    },
+};
+use actix_files::NamedFile;
 use actix_web::{
-    http::header::{HeaderName, HeaderValue},
+    http::{
+        header::{
+            self, Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue,
+            HeaderValue,
+        },
+        StatusCode,
+    },
+    web::BytesMut,
     Responder,
 };
-use std::collections::HashMap;
+use futures_util::stream::{self, Stream, StreamExt};
+use std::{collections::HashMap, io, path::Path};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The size of the chunks read by `HttpResponse::stream_reader`, in bytes.
+const READER_CHUNK_SIZE: usize = 8192;
 
 /// Represents an HTTP response being sent to the client.
 ///
@@ -34,7 +53,7 @@ use std::collections::HashMap;
 ///
 /// # Fields
 /// - `status_code` - HTTP status code (e.g., 200, 404, 500)
-/// - `body` - Response body content (JSON, text)
+/// - `body` - Response body content (JSON, text, stream)
 /// - `content_type` - Content-Type header value
 /// - `cookies` - Response cookies to be set
 /// - `headers` - Response headers
@@ -55,10 +74,13 @@ pub struct HttpResponse {
     cookies: HashMap<String, String>,
 
     // Sets response headers
-    headers: HashMap<String, String>,
+    headers: Headers,
 
     // Cookies to be removed
     remove_cookies: Vec<String>,
+
+    // Error to be handled by the error middlewares
+    error: Option<RipressError>,
 }
 
 impl HttpResponse {
@@ -84,8 +106,9 @@ impl HttpResponse {
             body: ResponseContentBody::TEXT(String::new()),
             content_type: ResponseContentType::JSON,
             cookies: HashMap::new(),
-            headers: HashMap::new(),
+            headers: Headers::new(),
             remove_cookies: Vec::new(),
+            error: None,
         }
     }
 
@@ -141,7 +164,9 @@ impl HttpResponse {
         return self;
     }
 
-    /// Sets a header in the response.
+    /// Sets a header in the response, replacing its previous values.
+    ///
+    /// An invalid header name or value fails the response with a `RipressError`.
     ///
     /// # Example
     /// ```
@@ -149,12 +174,49 @@ impl HttpResponse {
     /// let res = HttpResponse::new();
     /// res.set_header("key", "value"); // Sets the key cookie to value
     /// ```
-
-    pub fn set_header(mut self, key: &str, value: &str) -> Self {
-        self.headers.insert(key.to_string(), value.to_string());
+    pub fn set_header(mut self, key: &str, value: impl AsRef<[u8]>) -> Self {
+        if let Err(err) = self.headers.insert(key, value) {
+            self.error = Some(RipressError::other(err));
+        }
         return self;
     }
 
+    /// Adds a value to a header in the response, keeping its previous values.
+    ///
+    /// An invalid header name or value fails the response with a `RipressError`.
+    ///
+    /// # Example
+    /// ```
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let res = HttpResponse::new()
+    ///     .append_header("Link", "</style.css>; rel=preload")
+    ///     .append_header("Link", "</app.js>; rel=preload");
+    ///
+    /// assert_eq!(res.get_headers().get_all("link").len(), 2);
+    /// ```
+    pub fn append_header(mut self, key: &str, value: impl AsRef<[u8]>) -> Self {
+        if let Err(err) = self.headers.append(key, value) {
+            self.error = Some(RipressError::other(err));
+        }
+        self
+    }
+
+    /// Removes all values of a header from the response.
+    ///
+    /// # Example
+    /// ```
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let res = HttpResponse::new().set_header("X-Powered-By", "ripress");
+    /// let res = res.remove_header("x-powered-by");
+    /// assert!(res.get_header("X-Powered-By").is_err());
+    /// ```
+    pub fn remove_header(mut self, key: &str) -> Self {
+        self.headers.remove(key);
+        self
+    }
+
     /// Gets a header from the response.
     ///
     /// # Arguments
@@ -183,7 +245,7 @@ impl HttpResponse {
         let header = self.headers.get(key);
 
         match header {
-            Some(header_string) => Ok(header_string.clone()),
+            Some(header_string) => Ok(header_string.to_string()),
             None => Err(HttpResponseError::MissingHeader(key.to_string())),
         }
     }
@@ -326,6 +388,10 @@ impl HttpResponse {
 
     /// Sets the Content-Type of the response.
     ///
+    /// The body setters (`json`, `text`, `bytes`...) set their own content
+    /// type, so set it after the body. A `Content-Type` set with `set_header`
+    /// takes precedence.
+    ///
     /// # Arguments
     ///
     /// * `content_type` - The `ResponseContentType` to set
@@ -350,6 +416,64 @@ impl HttpResponse {
         return self;
     }
 
+    /// Sets the Content-Type of the response to any media type.
+    ///
+    /// Media types with a built-in `ResponseContentType` use it, e.g. `text/css`.
+    /// Like `set_content_type`, call it after setting the body. An invalid
+    /// header value fails the response with a `RipressError`.
+    ///
+    /// # Arguments
+    ///
+    /// * `content_type` - The media type, with parameters if any
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` for method chaining
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let res = HttpResponse::new()
+    ///     .ok()
+    ///     .text("id,name\n1,Alice\n")
+    ///     .content_type("text/csv; charset=utf-8");
+    /// ```
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        if let Err(err) = HeaderValue::from_str(content_type) {
+            self.error = Some(RipressError::other(err));
+            return self;
+        }
+        self.content_type = ResponseContentType::from(content_type);
+        self
+    }
+
+    /// Sets the response body to binary data, sent as `application/octet-stream`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Anything that converts into `Bytes`, e.g. a `Vec<u8>`
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` for method chaining
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let pdf: Vec<u8> = vec![0x25, 0x50, 0x44, 0x46];
+    /// let res = HttpResponse::new()
+    ///     .ok()
+    ///     .bytes(pdf)
+    ///     .content_type("application/pdf");
+    /// ```
+    pub fn bytes(mut self, bytes: impl Into<Bytes>) -> Self {
+        self.body = ResponseContentBody::BINARY(bytes.into());
+        self.content_type = ResponseContentType::BINARY;
+        self
+    }
+
     /// Sets the response body to JSON.
     ///
     /// # Arguments
@@ -448,30 +572,402 @@ impl HttpResponse {
         return self;
     }
 
+    /// Sets the response body to a stream, sent in chunks as it is produced.
+    ///
+    /// The response uses chunked transfer encoding, so large bodies such as
+    /// exports don't have to be built in memory. The `Content-Type` is
+    /// `application/octet-stream` unless set afterwards. If the stream
+    /// fails, the connection is closed before the body is complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - A stream of chunks, anything that converts into `Bytes`
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` for method chaining
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::stream;
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let rows = vec![(1, "Alice"), (2, "Bob")];
+    /// let lines = stream::iter(rows.into_iter().map(|(id, name)| {
+    ///     Ok::<_, std::io::Error>(format!("{},{}\n", id, name))
+    /// }));
+    ///
+    /// let res = HttpResponse::new()
+    ///     .ok()
+    ///     .set_header("Content-Type", "text/csv")
+    ///     .stream(lines);
+    /// ```
+    pub fn stream<S, T, E>(mut self, stream: S) -> Self
+    where
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        T: Into<Bytes>,
+        E: Into<BoxError>,
+    {
+        let stream = stream.map(|chunk| chunk.map(Into::into).map_err(Into::into));
+        self.body = ResponseContentBody::STREAM(ResponseStream(Box::pin(stream)));
+        self.content_type = ResponseContentType::BINARY;
+        self
+    }
+
+    /// Sets the response body to the contents of a reader, e.g. a file, sent in chunks.
+    ///
+    /// See `stream` for how the body is sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Any `AsyncRead`, read until it ends
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` for method chaining
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use ripress::context::{HttpRequest, HttpResponse};
+    ///
+    /// async fn export(_req: HttpRequest, res: HttpResponse) -> HttpResponse {
+    ///     match tokio::fs::File::open("export.csv").await {
+    ///         Ok(file) => res.ok().set_header("Content-Type", "text/csv").stream_reader(file),
+    ///         Err(_) => res.not_found().text("No export yet"),
+    ///     }
+    /// }
+    /// ```
+    pub fn stream_reader<R>(self, reader: R) -> Self
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        self.stream(read_chunks(reader))
+    }
+
+    /// Sends a file from disk, with a MIME type guessed from its extension.
+    ///
+    /// The file is streamed rather than read into memory. `Range` and
+    /// `If-Range` requests get the requested part with a 206 response, or a
+    /// 416 response if the range can't be satisfied, and the response has
+    /// `ETag` and `Last-Modified` headers. Images, text, audio and video are
+    /// sent inline, other files as attachments, see `download` to set the name.
+    ///
+    /// Ranges are only supported for 200 responses, setting another status
+    /// sends the whole file with it. Use `content_type` afterwards to replace
+    /// the guessed MIME type. Files that can't be sent, e.g. with an
+    /// unsatisfiable range, get a `text/plain` response instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` for method chaining, failed with a 404 error if the file doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use ripress::context::{HttpRequest, HttpResponse};
+    ///
+    /// async fn avatar(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+    ///     let user = req.get_params("user").unwrap_or_default();
+    ///     // Don't build paths from request values without checking them
+    ///     if !user.chars().all(|c| c.is_ascii_alphanumeric()) {
+    ///         return res.bad_request().text("Invalid user");
+    ///     }
+    ///     res.ok().send_file(format!("avatars/{}.png", user))
+    /// }
+    /// ```
+    pub fn send_file(self, path: impl AsRef<Path>) -> Self {
+        self.file(path.as_ref(), None)
+    }
+
+    /// Sends a file from disk as an attachment, prompting browsers to save it as `filename`.
+    ///
+    /// The file is sent like with `send_file`, with a `Content-Disposition:
+    /// attachment` header. Names that aren't ASCII are sent in the `filename*`
+    /// parameter, with an ASCII fallback.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file
+    /// * `filename` - The name the file is saved as
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` for method chaining, failed with a 404 error if the file doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use ripress::context::{HttpRequest, HttpResponse};
+    ///
+    /// async fn invoice(_req: HttpRequest, res: HttpResponse) -> HttpResponse {
+    ///     res.ok().download("storage/invoices/2024-001.pdf", "invoice-2024-001.pdf")
+    /// }
+    /// ```
+    pub fn download(self, path: impl AsRef<Path>, filename: &str) -> Self {
+        self.file(path.as_ref(), Some(filename.to_string()))
+    }
+
+    fn file(mut self, path: &Path, download_name: Option<String>) -> Self {
+        // Checked now so that missing files go through the error middlewares
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => {}
+            Ok(_) => return self.error(RipressError::Status(404, "File not found".to_string())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return self.error(RipressError::Status(404, "File not found".to_string()))
+            }
+            Err(err) => return self.error(RipressError::other(err)),
+        }
+
+        let media_type = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(actix_files::file_extension_to_mime)
+            .unwrap_or(actix_web::mime::APPLICATION_OCTET_STREAM);
+        self.content_type = ResponseContentType::from(media_type.essence_str());
+        self.body = ResponseContentBody::FILE(ResponseFile {
+            path: path.to_path_buf(),
+            download_name,
+        });
+        self
+    }
+
+    /// Sets the response body to Server-Sent Events, sent as they are produced.
+    ///
+    /// Sets `Content-Type: text/event-stream` and sends a keep-alive comment
+    /// every 15 seconds without events. Return an `Sse` from the handler to
+    /// change the keep-alive or be notified of disconnects.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - A stream of `Event`s
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` for method chaining
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::stream;
+    /// use ripress::{context::HttpResponse, sse::Event};
+    ///
+    /// let events = stream::iter(vec![
+    ///     Event::new().event("progress").data("50"),
+    ///     Event::new().event("progress").data("100"),
+    /// ]);
+    ///
+    /// let res = HttpResponse::new().ok().sse(events);
+    /// ```
+    pub fn sse<S>(self, events: S) -> Self
+    where
+        S: Stream<Item = Event> + Send + 'static,
+    {
+        Sse::new(events).into_response(self)
+    }
+
+    /// Marks the response as failed with an error.
+    ///
+    /// The error is passed to the error middlewares added with
+    /// `App::use_error_middleware`, which produce the final response. Without
+    /// error middlewares, the response gets the error's status code and message.
+    ///
+    /// # Arguments
+    ///
+    /// * `err` - The error, anything that converts into a `RipressError`
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` for method chaining
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::{context::HttpResponse, types::RipressError};
+    ///
+    /// let res = HttpResponse::new()
+    ///     .error(RipressError::Status(404, String::from("User not found")));
+    /// ```
+    pub fn error<E: Into<RipressError>>(mut self, err: E) -> Self {
+        self.error = Some(err.into());
+        self
+    }
+
+    /// Returns the error the response was marked with, if any.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::{context::HttpResponse, types::RipressError};
+    ///
+    /// let res = HttpResponse::new().error(RipressError::Status(409, String::from("Conflict")));
+    /// assert_eq!(res.get_error().unwrap().status_code(), 409);
+    /// ```
+    pub fn get_error(&self) -> Option<&RipressError> {
+        self.error.as_ref()
+    }
+
+    /// Removes and returns the error the response was marked with.
+    pub(crate) fn take_error(&mut self) -> Option<RipressError> {
+        self.error.take()
+    }
+
+    /// Returns a copy of the status, headers and cookies of the response, with an empty body.
+    pub(crate) fn head(&self) -> HttpResponse {
+        HttpResponse {
+            status_code: self.status_code,
+            body: ResponseContentBody::TEXT(String::new()),
+            content_type: self.content_type.clone(),
+            cookies: self.cookies.clone(),
+            headers: self.headers.clone(),
+            remove_cookies: self.remove_cookies.clone(),
+            error: None,
+        }
+    }
+
+    /// Returns the status code of the response.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let res = HttpResponse::new().not_found();
+    /// assert_eq!(res.get_status_code(), 404);
+    /// ```
+    pub fn get_status_code(&self) -> i32 {
+        self.status_code
+    }
+
+    /// Returns the content type of the response.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::{context::HttpResponse, types::ResponseContentType};
+    ///
+    /// let res = HttpResponse::new().html("<h1>Hello</h1>");
+    /// assert_eq!(res.get_content_type(), ResponseContentType::HTML);
+    /// ```
+    pub fn get_content_type(&self) -> ResponseContentType {
+        self.content_type.clone()
+    }
+
+    /// Returns all headers set on the response.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let res = HttpResponse::new().set_header("X-Custom", "value");
+    ///
+    /// for (key, value) in res.get_headers().iter() {
+    ///     println!("{}: {}", key, String::from_utf8_lossy(value));
+    /// }
+    /// ```
+    pub fn get_headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Returns the headers of the response for modification.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let mut res = HttpResponse::new();
+    /// res.headers_mut().append("Vary", "Origin").unwrap();
+    /// res.headers_mut().append("Vary", "Accept-Encoding").unwrap();
+    /// ```
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
+    /// Returns the size of the response body in bytes, as it will be sent.
+    ///
+    /// Streamed bodies have no known size, their size is 0.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ripress::context::HttpResponse;
+    ///
+    /// let res = HttpResponse::new().text("Hello");
+    /// assert_eq!(res.get_body_size(), 5);
+    /// ```
+    pub fn get_body_size(&self) -> usize {
+        match &self.body {
+            ResponseContentBody::JSON(json) => json.to_string().len(),
+            ResponseContentBody::TEXT(text) => text.len(),
+            ResponseContentBody::HTML(html) => html.len(),
+            ResponseContentBody::BINARY(bytes) => bytes.len(),
+            ResponseContentBody::STREAM(_) => 0,
+            ResponseContentBody::FILE(_) => 0,
+        }
+    }
+
     pub fn to_responder(self) -> actix_web::HttpResponse {
+        self.into_actix_response(None)
+    }
+
+    /// Converts the response, `req` is needed to answer `Range` requests for files.
+    pub(crate) fn into_actix_response(
+        mut self,
+        req: Option<&actix_web::HttpRequest>,
+    ) -> actix_web::HttpResponse {
+        // Errors that were not handled by an error middleware are sent as is
+        if let Some(err) = self.error.take() {
+            self = self.status(err.status_code()).text(err.to_string());
+        }
+
         let body = self.body;
+        let is_file = matches!(body, ResponseContentBody::FILE(_));
         let mut actix_res = actix_web::http::StatusCode::from_u16(self.status_code as u16)
-            .map(|status| match body {
-                ResponseContentBody::JSON(json) => actix_web::HttpResponse::build(status)
-                    .content_type("application/json")
-                    .json(json),
-                ResponseContentBody::TEXT(text) => actix_web::HttpResponse::build(status)
-                    .content_type("text/plain")
-                    .body(text),
-                ResponseContentBody::HTML(html) => actix_web::HttpResponse::build(status)
-                    .content_type("text/html")
-                    .body(html),
+            .map(|status| {
+                let mut builder = actix_web::HttpResponse::build(status);
+                match body {
+                    ResponseContentBody::JSON(json) => builder.json(json),
+                    ResponseContentBody::TEXT(text) => builder.body(text),
+                    ResponseContentBody::HTML(html) => builder.body(html),
+                    ResponseContentBody::BINARY(bytes) => builder.body(bytes),
+                    ResponseContentBody::STREAM(stream) => builder.streaming(
+                        stream
+                            .0
+                            .map(|chunk| chunk.map_err(|err| err as Box<dyn std::error::Error>)),
+                    ),
+                    ResponseContentBody::FILE(file) => file_response(file, status, req),
+                }
             })
             .unwrap_or_else(|_| {
                 actix_web::HttpResponse::InternalServerError().body("Invalid status code")
             });
 
-        self.headers.iter().for_each(|(key, value)| {
-            actix_res.headers_mut().append(
-                HeaderName::from_bytes(key.as_bytes()).unwrap(),
-                HeaderValue::from_str(value).unwrap(),
-            )
-        });
+        // Files that can't be sent, e.g. removed or with an unsatisfiable range, get a
+        // plain text response instead of the content type of the file
+        let status = actix_res.status();
+        let file_failed = is_file && (status.is_client_error() || status.is_server_error());
+        // A content type set with `set_header` is appended below, and replaces this one,
+        // except on the fallback response of a file
+        let custom_content_type = self.headers.contains("content-type");
+
+        if file_failed {
+            actix_res
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        } else if custom_content_type {
+            actix_res.headers_mut().remove(header::CONTENT_TYPE);
+        } else {
+            match HeaderValue::from_str(self.content_type.as_str()) {
+                Ok(value) => {
+                    actix_res.headers_mut().insert(header::CONTENT_TYPE, value);
+                }
+                Err(_) => {
+                    return actix_web::HttpResponse::InternalServerError()
+                        .body("Invalid content type")
+                }
+            }
+        }
+
+        self.headers
+            .as_header_map()
+            .iter()
+            .filter(|(key, _)| !file_failed || *key != header::CONTENT_TYPE)
+            .for_each(|(key, value)| actix_res.headers_mut().append(key.clone(), value.clone()));
 
         self.remove_cookies.iter().for_each(|key| {
             actix_res
@@ -489,24 +985,146 @@ impl HttpResponse {
     }
 }
 
-impl Responder for HttpResponse {
-    type Body = actix_web::body::BoxBody;
+/// Reads an `AsyncRead` in chunks until it ends.
+fn read_chunks<R>(reader: R) -> impl Stream<Item = io::Result<Bytes>> + Send
+where
+    R: AsyncRead + Send + 'static,
+{
+    stream::unfold(Some(Box::pin(reader)), |reader| async move {
+        let mut reader = reader?;
+        let mut chunk = BytesMut::with_capacity(READER_CHUNK_SIZE);
+        match reader.read_buf(&mut chunk).await {
+            Ok(0) => None,
+            Ok(_) => Some((Ok(chunk.freeze()), Some(reader))),
+            // End the stream after the error
+            Err(err) => Some((Err(err), None)),
+        }
+    })
+}
+
+/// The `Content-Disposition` of a download, with an ASCII fallback for other names.
+fn attachment(filename: &str) -> ContentDisposition {
+    let fallback = filename
+        .chars()
+        .map(|char| {
+            if char.is_ascii() && !char.is_ascii_control() {
+                char
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut parameters = vec![DispositionParam::Filename(fallback)];
+    if !filename.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: filename.as_bytes().to_vec(),
+        }));
+    }
 
-    fn respond_to(self, _req: &actix_web::HttpRequest) -> actix_web::HttpResponse {
-        self.to_responder()
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters,
     }
 }
 
-#[cfg(test)]
-impl HttpResponse {
-    pub(crate) fn get_status_code(&self) -> i32 {
-        self.status_code
+fn open_file(file: &ResponseFile) -> io::Result<NamedFile> {
+    let named = NamedFile::open(&file.path)?;
+    Ok(match &file.download_name {
+        Some(name) => named.set_content_disposition(attachment(name)),
+        None => named,
+    })
+}
+
+/// The response of a file that can't be opened, e.g. removed since the response was built.
+fn file_error(err: io::Error) -> actix_web::HttpResponse {
+    match err.kind() {
+        io::ErrorKind::NotFound => actix_web::HttpResponse::NotFound().body("File not found"),
+        _ => actix_web::HttpResponse::InternalServerError().body(err.to_string()),
     }
+}
 
-    pub(crate) fn get_content_type(&self) -> ResponseContentType {
-        self.content_type.clone()
+/// Sends the whole file with `status`.
+fn whole_file(named: NamedFile, status: StatusCode) -> actix_web::HttpResponse {
+    let content_type = named.content_type().to_string();
+    let disposition = named.content_disposition().to_string();
+    let file = match named.file().try_clone() {
+        Ok(file) => tokio::fs::File::from_std(file),
+        Err(err) => return file_error(err),
+    };
+    actix_web::HttpResponse::build(status)
+        .content_type(content_type)
+        .insert_header((header::CONTENT_DISPOSITION, disposition))
+        .streaming(read_chunks(file))
+}
+
+/// Returns `false` if the request's `If-Range` doesn't match the file sent in `res`.
+fn if_range_matches(req: &actix_web::HttpRequest, res: &actix_web::HttpResponse) -> bool {
+    let if_range = match req.headers().get(header::IF_RANGE) {
+        Some(if_range) => if_range,
+        None => return true,
+    };
+    // Weak entity tags never match
+    if if_range.as_bytes().starts_with(b"W/") {
+        return false;
+    }
+    let validator = if if_range.as_bytes().starts_with(b"\"") {
+        res.headers().get(header::ETAG)
+    } else {
+        res.headers().get(header::LAST_MODIFIED)
+    };
+    validator == Some(if_range)
+}
+
+fn file_response(
+    file: ResponseFile,
+    status: StatusCode,
+    req: Option<&actix_web::HttpRequest>,
+) -> actix_web::HttpResponse {
+    let named = match open_file(&file) {
+        Ok(named) => named,
+        Err(err) => return file_error(err),
+    };
+
+    match req {
+        // Ranges and conditional requests are answered by actix-files
+        Some(req) if status == StatusCode::OK => {
+            let res = named.into_response(req);
+            let ranged = matches!(
+                res.status(),
+                StatusCode::PARTIAL_CONTENT | StatusCode::RANGE_NOT_SATISFIABLE
+            );
+            if !ranged || if_range_matches(req, &res) {
+                return res;
+            }
+
+            // The file changed since the client got its validator, send all of it
+            let mut whole = match open_file(&file) {
+                Ok(named) => whole_file(named, status),
+                Err(err) => return file_error(err),
+            };
+            for name in [header::ETAG, header::LAST_MODIFIED, header::ACCEPT_RANGES] {
+                if let Some(value) = res.headers().get(&name) {
+                    whole.headers_mut().insert(name, value.clone());
+                }
+            }
+            whole
+        }
+        _ => whole_file(named, status),
+    }
+}
+
+impl Responder for HttpResponse {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, req: &actix_web::HttpRequest) -> actix_web::HttpResponse {
+        self.into_actix_response(Some(req))
     }
+}
 
+#[cfg(test)]
+impl HttpResponse {
     pub(crate) fn get_body(self) -> ResponseContentBody {
         self.body
     }
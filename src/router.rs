@@ -0,0 +1,320 @@
+use crate::types::{HttpMethods, RouterError};
+use percent_encoding::percent_decode_str;
+use std::collections::HashMap;
+
+/// A segment in a route pattern.
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    /// A literal segment, e.g. `users` in `/users/{id}`.
+    Static(&'a str),
+    /// A named parameter matching exactly one segment, e.g. `{id}`.
+    Param(&'a str),
+    /// A named parameter matching the rest of the path, e.g. `{path}*` or `*`.
+    CatchAll(&'a str),
+}
+
+/// Splits a path into its non-empty segments, along with their byte offsets.
+fn split_path(path: &str) -> impl Iterator<Item = (usize, &str)> {
+    path.split('/')
+        .scan(0, |offset, segment| {
+            let start = *offset;
+            *offset += segment.len() + 1;
+            Some((start, segment))
+        })
+        .filter(|(_, segment)| !segment.is_empty())
+}
+
+/// Parses a route pattern into segments.
+///
+/// ## Returns
+///
+/// Returns `Err(RouterError::InvalidPattern)` if a segment mixes literal text
+/// and a parameter, if a catch-all segment is not the last one, or if a
+/// parameter has a regex constraint such as `{id:\d+}`, which isn't supported.
+fn parse_pattern(pattern: &str) -> Result<Vec<Segment<'_>>, RouterError> {
+    let invalid = |reason: &str| RouterError::InvalidPattern {
+        pattern: pattern.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let raw_segments: Vec<&str> = split_path(pattern).map(|(_, raw)| raw).collect();
+    let mut segments = Vec::with_capacity(raw_segments.len());
+
+    for (index, raw) in raw_segments.iter().enumerate() {
+        let is_last = index == raw_segments.len() - 1;
+
+        let segment = if *raw == "*" {
+            Segment::CatchAll("*")
+        } else if let Some(inner) = raw.strip_prefix('{') {
+            let (name, catch_all) = match inner.strip_suffix("}*") {
+                Some(name) => (name, true),
+                None => match inner.strip_suffix('}') {
+                    Some(name) => (name, false),
+                    None => return Err(invalid("unclosed parameter")),
+                },
+            };
+
+            if name.contains(':') {
+                return Err(invalid(
+                    "regex constraints like {id:\\d+} aren't supported, validate the parameter in the handler",
+                ));
+            }
+            if name.is_empty() || name.contains(['{', '}', '/']) {
+                return Err(invalid("invalid parameter name"));
+            }
+
+            if catch_all {
+                Segment::CatchAll(name)
+            } else {
+                Segment::Param(name)
+            }
+        } else if raw.contains(['{', '}', '*']) {
+            return Err(invalid("parameters must span a whole segment"));
+        } else {
+            Segment::Static(raw)
+        };
+
+        if matches!(segment, Segment::CatchAll(_)) && !is_last {
+            return Err(invalid("catch-all parameters must be the last segment"));
+        }
+
+        segments.push(segment);
+    }
+
+    Ok(segments)
+}
+
+/// The result of looking up a method and path in the router.
+pub(crate) enum RouteMatch<'a, T> {
+    /// A route matched both the path and the method.
+    Found {
+        value: &'a T,
+        params: HashMap<String, String>,
+        pattern: &'a str,
+    },
+    /// The path matched, but not for this method. Holds the allowed methods.
+    MethodNotAllowed(Vec<HttpMethods>),
+    /// No route matched the path.
+    NotFound,
+}
+
+/// A route registered for a method, with the names of its parameters.
+struct Route<T> {
+    value: T,
+    pattern: String,
+    /// Parameter names, in the order of their segments.
+    params: Vec<String>,
+}
+
+struct Node<T> {
+    /// Literal children, keyed by segment.
+    statics: HashMap<String, Node<T>>,
+    /// Child matching any single segment. Parameter names are kept by each
+    /// route, so routes sharing a prefix can name it differently.
+    param: Option<Box<Node<T>>>,
+    /// Child holding the routes ending with a catch-all parameter.
+    catch_all: Option<Box<Node<T>>>,
+    /// Routes ending at this node, keyed by method.
+    routes: HashMap<HttpMethods, Route<T>>,
+}
+
+impl<T> Node<T> {
+    fn new() -> Self {
+        Node {
+            statics: HashMap::new(),
+            param: None,
+            catch_all: None,
+            routes: HashMap::new(),
+        }
+    }
+}
+
+/// A trie based router matching request paths segment by segment.
+///
+/// Static segments take precedence over `{param}` segments, which take
+/// precedence over catch-all segments. Registering two patterns matching the
+/// same paths for the same method, e.g. `/user/{id}` and `/user/{name}`, is
+/// rejected by `insert`.
+pub(crate) struct Router<T> {
+    root: Node<T>,
+}
+
+impl<T> Router<T> {
+    pub(crate) fn new() -> Self {
+        Router { root: Node::new() }
+    }
+
+    /// Registers a value for the method and route pattern.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(RouterError)` if the pattern is invalid or a route matching
+    /// the same paths is already registered for the method.
+    pub(crate) fn insert(
+        &mut self,
+        method: HttpMethods,
+        pattern: &str,
+        value: T,
+    ) -> Result<(), RouterError> {
+        let segments = parse_pattern(pattern)?;
+        let mut params = Vec::new();
+        let mut node = &mut self.root;
+
+        for segment in segments {
+            node = match segment {
+                Segment::Static(literal) => node
+                    .statics
+                    .entry(literal.to_string())
+                    .or_insert_with(Node::new),
+                Segment::Param(name) => {
+                    params.push(name.to_string());
+                    node.param.get_or_insert_with(|| Box::new(Node::new()))
+                }
+                Segment::CatchAll(name) => {
+                    params.push(name.to_string());
+                    node.catch_all.get_or_insert_with(|| Box::new(Node::new()))
+                }
+            };
+        }
+
+        if let Some(existing) = node.routes.get(&method) {
+            return Err(RouterError::Conflict {
+                pattern: pattern.to_string(),
+                existing: existing.pattern.clone(),
+            });
+        }
+
+        node.routes.insert(
+            method,
+            Route {
+                value,
+                pattern: pattern.to_string(),
+                params,
+            },
+        );
+        Ok(())
+    }
+
+    /// Looks up the route for the method and path.
+    pub(crate) fn lookup(&self, method: &HttpMethods, path: &str) -> RouteMatch<'_, T> {
+        let segments: Vec<(usize, &str)> = split_path(path).collect();
+        let mut params = Vec::new();
+        let mut allowed = Vec::new();
+
+        match lookup_node(
+            &self.root,
+            path,
+            &segments,
+            method,
+            &mut params,
+            &mut allowed,
+        ) {
+            Some(node) => {
+                let route = &node.routes[method];
+                RouteMatch::Found {
+                    value: &route.value,
+                    params: route
+                        .params
+                        .iter()
+                        .zip(params)
+                        .map(|(name, value)| (name.clone(), decode(value)))
+                        .collect(),
+                    pattern: &route.pattern,
+                }
+            }
+            None if !allowed.is_empty() => {
+                allowed.sort_by_key(method_order);
+                RouteMatch::MethodNotAllowed(allowed)
+            }
+            None => RouteMatch::NotFound,
+        }
+    }
+}
+
+/// Walks the trie, preferring static, then param, then catch-all children.
+///
+/// The values of the parameters are collected into `params` in path order,
+/// and named after the route is found.
+///
+/// Methods of nodes that match the path but not the method are collected into
+/// `allowed` so the caller can answer with 405 Method Not Allowed.
+fn lookup_node<'a, 'p, T>(
+    node: &'a Node<T>,
+    path: &'p str,
+    segments: &[(usize, &'p str)],
+    method: &HttpMethods,
+    params: &mut Vec<&'p str>,
+    allowed: &mut Vec<HttpMethods>,
+) -> Option<&'a Node<T>> {
+    let Some(((offset, segment), rest)) = segments.split_first() else {
+        if node.routes.contains_key(method) {
+            return Some(node);
+        }
+        collect_allowed(node, allowed);
+
+        // An empty remainder still satisfies a catch-all, e.g. `/files/*` for `/files`.
+        let child = node.catch_all.as_ref()?;
+        let found = match_catch_all(child, method, allowed)?;
+        params.push("");
+        return Some(found);
+    };
+
+    if let Some(child) = node.statics.get(*segment) {
+        if let Some(found) = lookup_node(child, path, rest, method, params, allowed) {
+            return Some(found);
+        }
+    }
+
+    if let Some(child) = &node.param {
+        params.push(segment);
+        if let Some(found) = lookup_node(child, path, rest, method, params, allowed) {
+            return Some(found);
+        }
+        params.pop();
+    }
+
+    if let Some(child) = &node.catch_all {
+        if let Some(found) = match_catch_all(child, method, allowed) {
+            params.push(path[*offset..].trim_end_matches('/'));
+            return Some(found);
+        }
+    }
+
+    None
+}
+
+fn match_catch_all<'a, T>(
+    node: &'a Node<T>,
+    method: &HttpMethods,
+    allowed: &mut Vec<HttpMethods>,
+) -> Option<&'a Node<T>> {
+    if node.routes.contains_key(method) {
+        Some(node)
+    } else {
+        collect_allowed(node, allowed);
+        None
+    }
+}
+
+fn collect_allowed<T>(node: &Node<T>, allowed: &mut Vec<HttpMethods>) {
+    for method in node.routes.keys() {
+        if !allowed.contains(method) {
+            allowed.push(method.clone());
+        }
+    }
+}
+
+/// The position of a method in `Allow` headers, so they are the same on every run.
+fn method_order(method: &HttpMethods) -> usize {
+    match method {
+        HttpMethods::GET => 0,
+        HttpMethods::POST => 1,
+        HttpMethods::PUT => 2,
+        HttpMethods::DELETE => 3,
+        HttpMethods::PATCH => 4,
+    }
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
@@ -0,0 +1,278 @@
+//! Server-Sent Events.
+//!
+//! Return an `Sse` from a handler, or call `res.sse(events)`, to push a
+//! stream of `Event`s to the browser's `EventSource`. A keep-alive comment is
+//! sent when no event was sent for a while, so proxies don't close the
+//! connection. Clients reconnecting send the id of the last event they
+//! received, available with `req.last_event_id()`.
+
+use crate::{
+    context::HttpResponse,
+    types::{Bytes, IntoResponse},
+};
+use futures_util::stream::{Stream, StreamExt};
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{Instant, Sleep};
+
+/// How long to wait without events before sending a keep-alive comment, by default.
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A server-sent event.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::sse::Event;
+/// use std::time::Duration;
+///
+/// let event = Event::new()
+///     .event("update")
+///     .id("42")
+///     .retry(Duration::from_secs(5))
+///     .data("first line\nsecond line");
+///
+/// assert_eq!(
+///     event.to_string(),
+///     "event: update\nid: 42\nretry: 5000\ndata: first line\ndata: second line\n\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    data: Option<String>,
+    comment: Option<String>,
+}
+
+/// Removes line breaks, which would end a field early.
+fn single_line(value: impl Into<String>) -> String {
+    let mut value = value.into();
+    value.retain(|char| char != '\n' && char != '\r');
+    value
+}
+
+/// Splits a value on `\r\n`, `\r` and `\n`, which all end a line for clients.
+fn split_lines(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split("\r\n")
+        .flat_map(|line| line.split(['\r', '\n']))
+}
+
+impl Event {
+    /// Creates an empty event.
+    pub fn new() -> Self {
+        Event::default()
+    }
+
+    /// Sets the event type, dispatched to `addEventListener(type, ...)` in the browser.
+    ///
+    /// Line breaks are removed.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(single_line(event));
+        self
+    }
+
+    /// Sets the event id, sent back by reconnecting clients in `Last-Event-ID`.
+    ///
+    /// Line breaks are removed.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(single_line(id));
+        self
+    }
+
+    /// Sets how long the client waits before reconnecting after the connection is lost.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Sets the data of the event, multiple lines are sent as multiple `data:` fields.
+    pub fn data(mut self, data: impl Into<String>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Sets the data of the event to a value serialized as JSON.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Err(serde_json::Error)` if the value can't be serialized.
+    pub fn json_data(self, data: impl serde::Serialize) -> Result<Self, serde_json::Error> {
+        Ok(self.data(serde_json::to_string(&data)?))
+    }
+
+    /// Sets a comment, ignored by clients.
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(comment) = &self.comment {
+            for line in split_lines(comment) {
+                writeln!(f, ": {}", line)?;
+            }
+        }
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {}", event)?;
+        }
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {}", id)?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        if let Some(data) = &self.data {
+            for line in split_lines(data) {
+                writeln!(f, "data: {}", line)?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+/// A Server-Sent Events response, sending each event of a stream as it is produced.
+///
+/// The stream is dropped when the client disconnects, so channel based
+/// streams see their sender fail. Use `on_disconnect` to be notified.
+///
+/// ## Example
+///
+/// ```
+/// use futures_util::stream::{self, StreamExt};
+/// use ripress::{context::HttpRequest, sse::{Event, Sse}};
+/// use std::time::Duration;
+///
+/// async fn ticks(req: HttpRequest) -> Sse<impl futures_util::Stream<Item = Event> + Send> {
+///     // Resume after the last tick the client received
+///     let start: u64 = req.last_event_id().and_then(|id| id.parse().ok()).unwrap_or(0);
+///
+///     let events = stream::iter(start + 1..).then(|tick| async move {
+///         tokio::time::sleep(Duration::from_secs(1)).await;
+///         Event::new().id(tick.to_string()).data(format!("Tick {}", tick))
+///     });
+///
+///     Sse::new(events)
+///         .keep_alive(Duration::from_secs(30))
+///         .on_disconnect(|| println!("Client disconnected"))
+/// }
+/// ```
+pub struct Sse<S> {
+    events: S,
+    keep_alive: Option<Duration>,
+    on_disconnect: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl<S> Sse<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    /// Creates a response sending the events, with a keep-alive every 15 seconds.
+    pub fn new(events: S) -> Self {
+        Sse {
+            events,
+            keep_alive: Some(DEFAULT_KEEP_ALIVE),
+            on_disconnect: None,
+        }
+    }
+
+    /// Sets how long to wait without events before sending a keep-alive comment.
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Disables keep-alive comments.
+    pub fn no_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+
+    /// Calls `callback` if the client disconnects before the events ended.
+    pub fn on_disconnect(mut self, callback: impl FnOnce() + Send + 'static) -> Self {
+        self.on_disconnect = Some(Box::new(callback));
+        self
+    }
+}
+
+impl<S> IntoResponse for Sse<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        let body = SseBody {
+            events: Box::pin(self.events),
+            keep_alive: self.keep_alive,
+            sleep: None,
+            on_disconnect: self.on_disconnect,
+            finished: false,
+        };
+
+        res.set_header("Content-Type", "text/event-stream")
+            .set_header("Cache-Control", "no-cache")
+            .stream(body)
+    }
+}
+
+/// The body of an `Sse` response, interleaving the events with keep-alive comments.
+struct SseBody {
+    events: Pin<Box<dyn Stream<Item = Event> + Send>>,
+    keep_alive: Option<Duration>,
+    sleep: Option<Pin<Box<Sleep>>>,
+    on_disconnect: Option<Box<dyn FnOnce() + Send>>,
+    finished: bool,
+}
+
+impl Stream for SseBody {
+    type Item = Result<Bytes, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.events.poll_next_unpin(cx) {
+            Poll::Ready(Some(event)) => {
+                self.sleep = None;
+                return Poll::Ready(Some(Ok(Bytes::from(event.to_string()))));
+            }
+            Poll::Ready(None) => {
+                self.finished = true;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => {}
+        }
+
+        let interval = match self.keep_alive {
+            Some(interval) => interval,
+            None => return Poll::Pending,
+        };
+        // Created on the first poll, so that `Sse` can be built outside of a runtime
+        let sleep = self
+            .sleep
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(interval)));
+
+        match sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                sleep.as_mut().reset(Instant::now() + interval);
+                Poll::Ready(Some(Ok(Bytes::from_static(b": keep-alive\n\n"))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for SseBody {
+    fn drop(&mut self) {
+        if !self.finished {
+            if let Some(on_disconnect) = self.on_disconnect.take() {
+                on_disconnect();
+            }
+        }
+    }
+}
@@ -8,13 +8,14 @@ async fn _test_handler(_req: HttpRequest, res: HttpResponse) -> HttpResponse {
 mod tests {
 
     use crate::{
-        app::{box_future, App},
+        app::{box_future, App, DispatchTable},
         context::HttpResponse,
+        router::RouteMatch,
         tests::app_test::_test_handler,
     };
 
     use crate::context::HttpRequest;
-    use crate::types::{HttpMethods, Next};
+    use crate::types::{handler, HttpMethods, Next, RipressError};
     use std::time::Duration;
 
     #[test]
@@ -111,7 +112,7 @@ mod tests {
         app.all("/all", _test_handler);
 
         let handle = tokio::spawn(async move {
-            app.listen(3000, || {}).await;
+            app.listen(3000, |_| {}).await.unwrap();
         });
 
         tokio::time::sleep(Duration::from_secs(5)).await;
@@ -236,10 +237,11 @@ mod tests {
 
         // Spawn the server on port 3001 in a background task.
         let server_handle = tokio::spawn(async move {
-            app.listen(3001, || {
-                println!("Server started on 3001");
+            app.listen(3001, |addr| {
+                println!("Server started on {}", addr);
             })
-            .await;
+            .await
+            .unwrap();
         });
 
         // Allow the server some time to start.
@@ -256,4 +258,840 @@ mod tests {
         // Stop the server by aborting the task.
         server_handle.abort();
     }
+
+    async fn dispatch_request(
+        table: &DispatchTable,
+        req: actix_web::HttpRequest,
+    ) -> actix_web::HttpResponse {
+        use actix_web::FromRequest;
+
+        let mut payload = actix_web::dev::Payload::None;
+        let web_payload = actix_web::web::Payload::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+        table.dispatch(req, web_payload).await
+    }
+
+    #[test]
+    fn test_build_dispatch_table() {
+        let mut app = App::new();
+        app.get("/user/{id}", _test_handler);
+        app.post("/user", _test_handler);
+
+        let table = app.build_dispatch_table().unwrap();
+
+        match table.lookup(&HttpMethods::GET, "/user/42") {
+            RouteMatch::Found {
+                params, pattern, ..
+            } => {
+                assert_eq!(params.get("id").unwrap(), "42");
+                assert_eq!(pattern, "/user/{id}");
+            }
+            _ => panic!("Expected /user/42 to match"),
+        }
+
+        assert!(matches!(
+            table.lookup(&HttpMethods::POST, "/user"),
+            RouteMatch::Found { .. }
+        ));
+        assert!(matches!(
+            table.lookup(&HttpMethods::POST, "/user/42"),
+            RouteMatch::MethodNotAllowed(_)
+        ));
+        assert!(matches!(
+            table.lookup(&HttpMethods::GET, "/missing"),
+            RouteMatch::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_build_dispatch_table_conflict() {
+        let mut app = App::new();
+        app.get("/user/{id}", _test_handler);
+        app.post("/user/{name}", _test_handler);
+        assert!(app.build_dispatch_table().is_ok());
+
+        app.get("/user/{name}", _test_handler);
+        assert!(app.build_dispatch_table().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_params() {
+        let mut app = App::new();
+        app.get(
+            "/user/{id}",
+            |req: HttpRequest, res: HttpResponse| async move {
+                let id = req.get_params("id").unwrap().to_string();
+                assert_eq!(req.route_pattern(), Some("/user/{id}"));
+                res.ok().text(id)
+            },
+        );
+        app.patch("/user/{id}", _test_handler);
+        app.delete("/user/{id}", _test_handler);
+        let table = app.build_dispatch_table().unwrap();
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/user/7")
+            .to_http_request();
+
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 200);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "7");
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/unknown")
+            .to_http_request();
+        assert_eq!(dispatch_request(&table, req).await.status(), 404);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/user/7")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 405);
+        assert_eq!(
+            response.headers().get("Allow").unwrap(),
+            "GET, DELETE, PATCH"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_response_hooks() {
+        use std::sync::Mutex;
+
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let observed_clone = observed.clone();
+
+        let mut app = App::new();
+        app.get(
+            "/missing/{id}",
+            |_: HttpRequest, res: HttpResponse| async move { res.not_found().text("gone") },
+        );
+        app.on_response(move |req, res| {
+            observed_clone.lock().unwrap().push((
+                req.route_pattern().unwrap().to_string(),
+                res.get_status_code(),
+                res.get_body_size(),
+            ));
+        });
+        assert_eq!(app.get_response_hooks().len(), 1);
+
+        let table = app.build_dispatch_table().unwrap();
+        let req = actix_web::test::TestRequest::get()
+            .uri("/missing/1")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 404);
+
+        assert_eq!(
+            *observed.lock().unwrap(),
+            vec![("/missing/{id}".to_string(), 404, 4)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_response_hooks_see_middleware_data() {
+        use std::sync::Mutex;
+
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let observed_clone = observed.clone();
+
+        let mut app = App::new();
+        app.get("/user", _test_handler);
+        app.use_middleware("/", |mut req, res, next| async move {
+            req.set_data("user", "alice");
+            next.run(req, res).await
+        });
+        app.on_response(move |req, res| {
+            observed_clone
+                .lock()
+                .unwrap()
+                .push((req.get_data("user").cloned(), res.get_status_code()));
+        });
+
+        let table = app.build_dispatch_table().unwrap();
+        let req = actix_web::test::TestRequest::get()
+            .uri("/user")
+            .to_http_request();
+        assert_eq!(dispatch_request(&table, req).await.status(), 200);
+
+        assert_eq!(
+            *observed.lock().unwrap(),
+            vec![(Some("alice".to_string()), 200)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_response_hooks_see_unmatched_requests() {
+        use std::sync::Mutex;
+
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let observed_clone = observed.clone();
+
+        let mut app = App::new();
+        app.get("/user", _test_handler);
+        app.on_response(move |req, res| {
+            observed_clone.lock().unwrap().push((
+                req.get_path().to_string(),
+                req.route_pattern().map(String::from),
+                res.get_status_code(),
+            ));
+        });
+
+        let table = app.build_dispatch_table().unwrap();
+        let req = actix_web::test::TestRequest::get()
+            .uri("/missing")
+            .to_http_request();
+        assert_eq!(dispatch_request(&table, req).await.status(), 404);
+        let req = actix_web::test::TestRequest::delete()
+            .uri("/user")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 405);
+        assert_eq!(response.headers().get("Allow").unwrap(), "GET");
+
+        assert_eq!(
+            *observed.lock().unwrap(),
+            vec![
+                ("/missing".to_string(), None, 404),
+                ("/user".to_string(), None, 405),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_startup_hook_failure_aborts_listen() {
+        let mut app = App::new();
+        app.get("/", _test_handler);
+        app.on_startup(|| async { Err::<(), _>("database unavailable") });
+
+        let called = Arc::new(AtomicBool::new(false));
+        let called_clone = called.clone();
+
+        let result = app
+            .listen(3002, move |_| called_clone.store(true, Ordering::SeqCst))
+            .await;
+
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("database unavailable"));
+        assert!(!called.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_listen_rejects_conflicting_routes() {
+        let mut app = App::new();
+        app.get("/user/{id}", _test_handler);
+        app.get("/user/{name}", _test_handler);
+
+        let result = app.listen(3003, |_| {}).await;
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_startup_hook_runs_before_bind() {
+        let started = Arc::new(AtomicBool::new(false));
+        let started_clone = started.clone();
+
+        let mut app = App::new();
+        app.get("/", _test_handler);
+        app.on_startup(move || {
+            let started = started_clone.clone();
+            async move {
+                started.store(true, Ordering::SeqCst);
+                Ok::<(), std::io::Error>(())
+            }
+        });
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            app.listen(3004, move |addr| {
+                let _ = tx.send(addr);
+            })
+            .await
+            .unwrap();
+        });
+
+        let addr = tokio::time::timeout(Duration::from_secs(5), rx)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(addr.port(), 3004);
+        assert!(started.load(Ordering::SeqCst));
+
+        server_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_use_middleware_path_segment_boundary() {
+        let mut app = App::new();
+        let called = Arc::new(AtomicBool::new(false));
+        let called_clone = called.clone();
+        app.use_middleware(
+            "/auth",
+            move |req: HttpRequest, res: HttpResponse, next: Next| {
+                let called = called_clone.clone();
+                async move {
+                    called.store(true, Ordering::SeqCst);
+                    next.run(req, res).await
+                }
+            },
+        );
+
+        let middleware = app.get_middlewares()[0].clone_box();
+
+        // "/authors" shares a prefix but not a segment with "/auth".
+        let mut req = HttpRequest::new();
+        req.set_path("/authors".to_string());
+        let next = Next {
+            middleware: vec![],
+            handler: Arc::new(|_req, res| Box::pin(async move { res })),
+        };
+
+        let _ = middleware.handle(req, HttpResponse::new(), next).await;
+        assert!(!called.load(Ordering::SeqCst));
+    }
+
+    #[derive(Debug)]
+    struct UserNotFound;
+
+    impl std::fmt::Display for UserNotFound {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "user not found")
+        }
+    }
+
+    impl std::error::Error for UserNotFound {}
+
+    #[tokio::test]
+    async fn test_error_middleware() {
+        let mut app = App::new();
+        app.get(
+            "/user/{id}",
+            |_: HttpRequest, res: HttpResponse| async move {
+                res.error(RipressError::other(UserNotFound))
+            },
+        );
+        app.use_error_middleware(|err, req, res, next| async move {
+            if err.downcast_ref::<UserNotFound>().is_some() {
+                let message = format!("{} does not exist", req.get_path());
+                next.run(RipressError::Status(404, message), req, res).await
+            } else {
+                next.run(err, req, res).await
+            }
+        });
+        app.use_error_middleware(|err, _req, res, _next| async move {
+            res.status(err.status_code())
+                .json(serde_json::json!({ "error": err.to_string() }))
+        });
+        assert_eq!(app.get_error_middlewares().len(), 2);
+
+        let table = app.build_dispatch_table().unwrap();
+        let req = actix_web::test::TestRequest::get()
+            .uri("/user/7")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+
+        assert_eq!(response.status(), 404);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, r#"{"error":"/user/7 does not exist"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_error_middleware_sees_middleware_data() {
+        let mut app = App::new();
+        app.get("/user", |_: HttpRequest, res: HttpResponse| async move {
+            res.error(RipressError::other(UserNotFound))
+        });
+        app.get("/admin", _test_handler);
+        app.use_middleware("/", |mut req, res, next| async move {
+            req.set_data("user", "alice");
+            next.run(req, res).await
+        });
+        app.use_middleware("/admin", |_req, res, _next| async move {
+            res.error(RipressError::Status(403, String::from("Forbidden")))
+        });
+        app.use_error_middleware(|err, req, res, _next| async move {
+            let user = req.get_data("user").cloned().unwrap_or_default();
+            res.status(err.status_code())
+                .text(format!("{} for {}", err, user))
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        for (uri, status, expected) in [
+            ("/user", 500, "user not found for alice"),
+            ("/admin", 403, "Forbidden for alice"),
+        ] {
+            let req = actix_web::test::TestRequest::get()
+                .uri(uri)
+                .to_http_request();
+            let response = dispatch_request(&table, req).await;
+            assert_eq!(response.status(), status);
+            let body = actix_web::body::to_bytes(response.into_body())
+                .await
+                .unwrap();
+            assert_eq!(body, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_error_response() {
+        let mut app = App::new();
+        app.post("/", |_: HttpRequest, res: HttpResponse| async move {
+            res.error(RipressError::other(UserNotFound))
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 500);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "user not found");
+    }
+
+    #[tokio::test]
+    async fn test_error_middleware_invalid_body() {
+        let mut app = App::new();
+        app.post("/", _test_handler);
+        app.use_error_middleware(|err, _req, res, _next| async move {
+            assert!(matches!(err, RipressError::InvalidBody(_)));
+            res.bad_request().text("bad body")
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        let (req, mut payload) = actix_web::test::TestRequest::post()
+            .uri("/")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload("{invalid")
+            .to_http_parts();
+        let payload = {
+            use actix_web::FromRequest;
+            actix_web::web::Payload::from_request(&req, &mut payload)
+                .await
+                .unwrap()
+        };
+
+        let response = table.dispatch(req, payload).await;
+        assert_eq!(response.status(), 400);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "bad body");
+    }
+
+    #[tokio::test]
+    async fn test_extractor_handler() {
+        use crate::extract::{Json, Path, Query, State};
+
+        #[derive(serde::Deserialize)]
+        struct UserPath {
+            id: u32,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Greeting {
+            greeting: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Body {
+            name: String,
+        }
+
+        async fn greet(
+            Path(path): Path<UserPath>,
+            Query(query): Query<Greeting>,
+            Json(body): Json<Body>,
+            State(suffix): State<&'static str>,
+            res: HttpResponse,
+        ) -> HttpResponse {
+            res.ok().text(format!(
+                "{} {} #{}{}",
+                query.greeting, body.name, path.id, suffix
+            ))
+        }
+
+        let mut app = App::new();
+        app.with_state("!");
+        app.put("/user/{id}", handler(greet));
+        let table = app.build_dispatch_table().unwrap();
+
+        let (req, mut payload) = actix_web::test::TestRequest::put()
+            .uri("/user/7?greeting=Hello")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(r#"{"name": "John"}"#)
+            .to_http_parts();
+        let payload = {
+            use actix_web::FromRequest;
+            actix_web::web::Payload::from_request(&req, &mut payload)
+                .await
+                .unwrap()
+        };
+        let response = table.dispatch(req, payload).await;
+        assert_eq!(response.status(), 200);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "Hello John #7!");
+
+        // A non numeric id fails the extraction before the handler runs
+        let req = actix_web::test::TestRequest::put()
+            .uri("/user/abc?greeting=Hello")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_untyped_closure_handler() {
+        let mut app = App::new();
+        app.get("/user/{id}", |req, res| async move {
+            let id = req.get_params("id").unwrap().to_string();
+            res.ok().text(format!("User {}", id))
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/user/5")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "User 5");
+    }
+
+    #[tokio::test]
+    async fn test_into_response_handler_keeps_response() {
+        use crate::extract::Path;
+
+        #[derive(serde::Deserialize)]
+        struct UserPath {
+            id: u32,
+        }
+
+        async fn user(Path(path): Path<UserPath>, _res: HttpResponse) -> String {
+            format!("User {}", path.id)
+        }
+
+        let mut app = App::new();
+        app.get("/user/{id}", handler(user));
+        app.use_middleware("/", |req, res, next| async move {
+            next.run(req, res.set_header("X-Middleware", "yes")).await
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/user/5")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers().get("X-Middleware").unwrap(), "yes");
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "User 5");
+    }
+
+    #[tokio::test]
+    async fn test_into_response_handler() {
+        async fn hello() -> &'static str {
+            "Hello"
+        }
+
+        async fn user(req: HttpRequest) -> Result<String, RipressError> {
+            let id = req.get_params("id")?;
+            if id == "0" {
+                return Err(RipressError::Status(404, "No user 0".to_string()));
+            }
+            Ok(format!("User {}", id))
+        }
+
+        let mut app = App::new();
+        app.get("/", handler(hello));
+        app.get("/user/{id}", handler(user));
+        app.use_middleware("/", |req, res, next| async move {
+            next.run(req, res.set_header("X-Middleware", "yes")).await
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers().get("X-Middleware").unwrap(), "yes");
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "Hello");
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/user/5")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "User 5");
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/user/0")
+            .to_http_request();
+        let response = dispatch_request(&table, req).await;
+        assert_eq!(response.status(), 404);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "No user 0");
+    }
+
+    async fn dispatch_post(
+        table: &DispatchTable,
+        uri: &str,
+        content_type: &str,
+        body: &'static str,
+    ) -> actix_web::HttpResponse {
+        let (req, mut payload) = actix_web::test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("Content-Type", content_type))
+            .set_payload(body)
+            .to_http_parts();
+        let payload = {
+            use actix_web::FromRequest;
+            actix_web::web::Payload::from_request(&req, &mut payload)
+                .await
+                .unwrap()
+        };
+        table.dispatch(req, payload).await
+    }
+
+    #[tokio::test]
+    async fn test_body_limits() {
+        let mut app = App::new();
+        app.body_limit(8)
+            .body_limit_for(crate::types::RequestBodyType::JSON, 16);
+        app.post("/", _test_handler);
+        app.post("/upload", _test_handler).body_limit(32);
+        let table = app.build_dispatch_table().unwrap();
+
+        let response = dispatch_post(&table, "/", "text/plain", "short").await;
+        assert_eq!(response.status(), 200);
+
+        let response = dispatch_post(&table, "/", "text/plain", "longer than eight").await;
+        assert_eq!(response.status(), 413);
+
+        let response = dispatch_post(&table, "/", "application/json", r#"{"a":"bcdefg"}"#).await;
+        assert_eq!(response.status(), 200);
+
+        let response =
+            dispatch_post(&table, "/", "application/json", r#"{"a":"bcdefghijk"}"#).await;
+        assert_eq!(response.status(), 413);
+
+        let response = dispatch_post(&table, "/upload", "text/plain", "longer than eight").await;
+        assert_eq!(response.status(), 200);
+
+        let response = dispatch_post(
+            &table,
+            "/upload",
+            "text/plain",
+            "this body is longer than thirty-two bytes",
+        )
+        .await;
+        assert_eq!(response.status(), 413);
+    }
+
+    #[tokio::test]
+    async fn test_body_limit_error_middleware() {
+        let mut app = App::new();
+        app.body_limit(4);
+        app.post("/", _test_handler);
+        app.use_error_middleware(|err, _req, res, _next| async move {
+            assert!(matches!(err, RipressError::PayloadTooLarge(4)));
+            res.status(413).text("too large")
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        let response = dispatch_post(&table, "/", "text/plain", "hello").await;
+        assert_eq!(response.status(), 413);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "too large");
+    }
+
+    async fn dispatch_stream<T: Into<actix_web::web::Bytes>>(
+        table: &DispatchTable,
+        chunks: Vec<T>,
+    ) -> actix_web::HttpResponse {
+        use futures_util::stream;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/upload")
+            .to_http_request();
+        let chunks = chunks
+            .into_iter()
+            .map(|chunk| Ok(chunk.into()))
+            .collect::<Vec<_>>();
+        let stream: std::pin::Pin<
+            Box<dyn futures_util::Stream<Item = Result<_, actix_web::error::PayloadError>>>,
+        > = Box::pin(stream::iter(chunks));
+        let mut payload = actix_web::dev::Payload::from(stream);
+        let payload = {
+            use actix_web::FromRequest;
+            actix_web::web::Payload::from_request(&req, &mut payload)
+                .await
+                .unwrap()
+        };
+        table.dispatch(req, payload).await
+    }
+
+    #[tokio::test]
+    async fn test_stream_body() {
+        use futures_util::StreamExt;
+
+        async fn upload(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+            assert!(req.text().unwrap().is_empty());
+            let mut body = req.body_stream().unwrap();
+            assert!(req.body_stream().is_none());
+
+            let mut chunks = Vec::new();
+            while let Some(chunk) = body.next().await {
+                match chunk {
+                    Ok(chunk) => chunks.push(String::from_utf8(chunk.to_vec()).unwrap()),
+                    Err(err) => return res.error(err),
+                }
+            }
+            res.ok().text(chunks.join("|"))
+        }
+
+        let mut app = App::new();
+        app.post("/upload", upload).stream_body().body_limit(10);
+        app.use_middleware(
+            "/",
+            |req, res, next| async move { next.run(req, res).await },
+        );
+        let table = app.build_dispatch_table().unwrap();
+
+        let response = dispatch_stream(&table, vec!["abc", "def", "gh"]).await;
+        assert_eq!(response.status(), 200);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "abc|def|gh");
+
+        let response = dispatch_stream(&table, vec!["abcdef", "ghijkl"]).await;
+        assert_eq!(response.status(), 413);
+
+        let response = dispatch_post(&table, "/upload", "text/plain", "longer than ten").await;
+        assert_eq!(response.status(), 413);
+    }
+
+    #[tokio::test]
+    async fn test_stream_body_not_read() {
+        use crate::types::BodyStream;
+
+        async fn ignore(_body: BodyStream, res: HttpResponse) -> HttpResponse {
+            res.ok().text("ignored")
+        }
+
+        async fn buffered(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+            assert!(req.body_stream().is_none());
+            res.ok().text(req.text().unwrap())
+        }
+
+        let mut app = App::new();
+        app.post("/upload", handler(ignore)).stream_body();
+        app.post("/", buffered);
+        let table = app.build_dispatch_table().unwrap();
+
+        let chunks = vec!["chunk"; 64];
+        let response = dispatch_stream(&table, chunks).await;
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "ignored");
+
+        let response = dispatch_post(&table, "/", "text/plain", "buffered").await;
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "buffered");
+    }
+
+    #[tokio::test]
+    async fn test_stream_body_passed_through() {
+        async fn echo(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+            res.ok().stream(req.body_stream().unwrap())
+        }
+
+        let mut app = App::new();
+        app.post("/upload", echo).stream_body();
+        let table = app.build_dispatch_table().unwrap();
+
+        // More chunks than the stream buffers, so most are read while the response is sent
+        let chunks: Vec<String> = (0..64).map(|i| format!("{:02},", i)).collect();
+        let expected = chunks.concat();
+        let response = dispatch_stream(&table, chunks).await;
+        assert_eq!(response.status(), 200);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn test_stream_body_aborted() {
+        use crate::types::BodyStream;
+        use futures_util::StreamExt;
+        use std::sync::Mutex;
+
+        let received = Arc::new(Mutex::new(None));
+        let received_clone = received.clone();
+
+        let mut app = App::new();
+        app.post(
+            "/upload",
+            handler(move |mut body: BodyStream, res: HttpResponse| {
+                let received = received_clone.clone();
+                async move {
+                    // Responds without the body, which stops being read once the response is sent
+                    tokio::spawn(async move {
+                        let mut chunks = 0;
+                        let mut failed = false;
+                        while let Some(chunk) = body.next().await {
+                            match chunk {
+                                Ok(_) => chunks += 1,
+                                Err(_) => failed = true,
+                            }
+                        }
+                        *received.lock().unwrap() = Some((chunks, failed));
+                    });
+                    res.ok().text("accepted")
+                }
+            }),
+        )
+        .stream_body();
+        let table = app.build_dispatch_table().unwrap();
+
+        let response = dispatch_stream(&table, vec!["chunk"; 64]).await;
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "accepted");
+
+        while received.lock().unwrap().is_none() {
+            tokio::task::yield_now().await;
+        }
+        let (chunks, failed) = received.lock().unwrap().unwrap();
+        assert!(chunks < 64);
+        assert!(failed);
+    }
 }
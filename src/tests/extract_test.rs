@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    use crate::context::HttpRequest;
+    use crate::extract::{
+        Authorization, Form, FromRequest, Header, Json, Path, Query, State, TypedHeader, UserAgent,
+    };
+    use crate::types::{Bytes, HttpRequestError, RequestBodyType, RipressError};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Pagination {
+        page: u32,
+        per_page: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, serde::Serialize)]
+    struct User {
+        name: String,
+    }
+
+    #[test]
+    fn test_query_extractor() {
+        let mut req = HttpRequest::new();
+        req.set_query("page", "2");
+
+        let Query(query) = Query::<Pagination>::from_request(&req).unwrap();
+        assert_eq!(
+            query,
+            Pagination {
+                page: 2,
+                per_page: None
+            }
+        );
+
+        req.set_query("page", "two");
+        let err = Query::<Pagination>::from_request(&req).unwrap_err();
+        assert_eq!(err.status_code(), 400);
+    }
+
+    #[test]
+    fn test_bytes_extractor() {
+        let mut req = HttpRequest::new();
+        req.set_bytes(b"\x00\xff", RequestBodyType::BINARY);
+        assert_eq!(Bytes::from_request(&req).unwrap(), &b"\x00\xff"[..]);
+
+        req.set_json(json!({"name": "John"}), RequestBodyType::JSON);
+        assert_eq!(
+            Bytes::from_request(&req).unwrap(),
+            &b"{\"name\":\"John\"}"[..]
+        );
+    }
+
+    #[test]
+    fn test_path_extractor() {
+        let mut req = HttpRequest::new();
+        req.set_param("id", "42");
+
+        let Path(params) = Path::<HashMap<String, u32>>::from_request(&req).unwrap();
+        assert_eq!(params.get("id"), Some(&42));
+
+        assert!(Path::<Pagination>::from_request(&req).is_err());
+    }
+
+    #[test]
+    fn test_json_extractor() {
+        let mut req = HttpRequest::new();
+        req.set_json(json!({"name": "John"}), RequestBodyType::JSON);
+
+        let Json(user) = Json::<User>::from_request(&req).unwrap();
+        assert_eq!(user.name, "John");
+
+        req.set_json(json!({"age": 30}), RequestBodyType::JSON);
+        assert!(matches!(
+            Json::<User>::from_request(&req),
+            Err(RipressError::Extract(_))
+        ));
+
+        req.set_text("name=John", RequestBodyType::TEXT);
+        assert!(Json::<User>::from_request(&req).is_err());
+    }
+
+    #[test]
+    fn test_form_extractor() {
+        let mut req = HttpRequest::new();
+        req.set_form("name", "John", RequestBodyType::FORM);
+
+        let Form(user) = Form::<User>::from_request(&req).unwrap();
+        assert_eq!(user.name, "John");
+
+        req.set_json(json!({"name": "John"}), RequestBodyType::JSON);
+        assert!(Form::<User>::from_request(&req).is_err());
+    }
+
+    #[test]
+    fn test_header_extractor() {
+        #[derive(Debug)]
+        struct RequestId(u64);
+
+        impl TypedHeader for RequestId {
+            const NAME: &'static str = "X-Request-Id";
+
+            fn parse(value: &str) -> Result<Self, String> {
+                value
+                    .parse()
+                    .map(RequestId)
+                    .map_err(|_| String::from("not a number"))
+            }
+        }
+
+        let mut req = HttpRequest::new();
+        assert_eq!(
+            Header::<UserAgent>::from_request(&req)
+                .unwrap_err()
+                .to_string(),
+            HttpRequestError::MissingHeader("user-agent".to_string()).to_string()
+        );
+        assert_eq!(
+            Option::<Header<UserAgent>>::from_request(&req).unwrap(),
+            None
+        );
+
+        req.set_header("user-agent", "curl");
+        req.set_header("authorization", "Bearer secret");
+        req.set_header("x-request-id", "12");
+
+        let Header(agent) = Header::<UserAgent>::from_request(&req).unwrap();
+        assert_eq!(agent.0, "curl");
+        let Header(auth) = Header::<Authorization>::from_request(&req).unwrap();
+        assert_eq!(auth.bearer(), Some("secret"));
+        let Header(id) = Header::<RequestId>::from_request(&req).unwrap();
+        assert_eq!(id.0, 12);
+
+        req.set_header("x-request-id", "abc");
+        let err = Header::<RequestId>::from_request(&req).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid header X-Request-Id: not a number");
+    }
+
+    #[test]
+    fn test_state_extractor() {
+        let req = HttpRequest::new();
+        let err = State::<String>::from_request(&req).unwrap_err();
+        assert_eq!(err.status_code(), 500);
+        assert!(req.get_state::<String>().is_none());
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use crate::types::{HeaderError, Headers};
+
+    #[test]
+    fn test_case_insensitive_lookup() {
+        let mut headers = Headers::new();
+        headers.insert("Content-Type", "text/plain").unwrap();
+
+        assert_eq!(headers.get("content-type"), Some("text/plain"));
+        assert_eq!(headers.get("CONTENT-TYPE"), Some("text/plain"));
+        assert!(headers.contains("Content-type"));
+        assert_eq!(headers.get("accept"), None);
+        assert_eq!(headers.get("invalid name"), None);
+    }
+
+    #[test]
+    fn test_multiple_values() {
+        let mut headers = Headers::new();
+        headers.append("Link", "</a.css>").unwrap();
+        headers.append("link", "</b.js>").unwrap();
+
+        assert_eq!(headers.get("link"), Some("</a.css>"));
+        assert_eq!(headers.get_all("LINK"), vec!["</a.css>", "</b.js>"]);
+        assert_eq!(headers.len(), 2);
+
+        headers.insert("Link", "</c.png>").unwrap();
+        assert_eq!(headers.get_all("link"), vec!["</c.png>"]);
+
+        assert!(headers.remove("LINK"));
+        assert!(!headers.remove("link"));
+        assert!(headers.is_empty());
+    }
+
+    #[test]
+    fn test_byte_values() {
+        let mut headers = Headers::new();
+        headers.insert("X-Binary", b"caf\xe9").unwrap();
+        headers.append("X-Binary", "plain").unwrap();
+
+        assert_eq!(headers.get_bytes("x-binary"), Some(&b"caf\xe9"[..]));
+        assert_eq!(headers.get("x-binary"), None);
+        assert_eq!(headers.get_all("x-binary"), vec!["plain"]);
+        assert_eq!(headers.get_all_bytes("x-binary").len(), 2);
+
+        let names: Vec<&str> = headers.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["x-binary", "x-binary"]);
+    }
+
+    #[test]
+    fn test_invalid_headers() {
+        let mut headers = Headers::new();
+
+        assert_eq!(
+            headers.insert("bad name", "value"),
+            Err(HeaderError::InvalidName("bad name".to_string()))
+        );
+        assert_eq!(
+            headers.append("X-Bad", "line\nbreak"),
+            Err(HeaderError::InvalidValue("X-Bad".to_string()))
+        );
+        assert!(headers.is_empty());
+    }
+}
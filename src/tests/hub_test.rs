@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app::App,
+        context::HttpRequest,
+        extract::State,
+        hub::{Backpressure, Hub, HubError},
+        types::handler,
+    };
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_hub_rooms() {
+        let hub = Hub::<String>::new();
+        let mut alice = hub.subscribe();
+        let mut bob = hub.subscribe();
+        assert_eq!(hub.client_count(), 2);
+
+        assert!(alice.join("rust"));
+        assert!(bob.join("rust"));
+        assert!(bob.join("go"));
+        assert_eq!(hub.members("rust").len(), 2);
+
+        assert_eq!(hub.broadcast_to("rust", "hello rust".to_string()), 2);
+        assert_eq!(hub.broadcast_to("go", "hello go".to_string()), 1);
+        assert_eq!(hub.broadcast_to("python", "hello?".to_string()), 0);
+        assert_eq!(hub.broadcast("hello all".to_string()), 2);
+
+        assert_eq!(alice.recv().await.unwrap(), "hello rust");
+        assert_eq!(alice.recv().await.unwrap(), "hello all");
+        assert_eq!(bob.next().await.unwrap(), "hello rust");
+        assert_eq!(bob.next().await.unwrap(), "hello go");
+        assert_eq!(bob.next().await.unwrap(), "hello all");
+
+        assert!(bob.leave("go"));
+        assert!(!bob.leave("go"));
+        assert!(hub.members("go").is_empty());
+        assert_eq!(hub.broadcast_to("go", "anyone?".to_string()), 0);
+
+        hub.send(alice.id(), "direct".to_string()).await.unwrap();
+        assert_eq!(alice.recv().await.unwrap(), "direct");
+
+        // Dropping a subscription removes it from the hub and its rooms
+        let bob_id = bob.id();
+        drop(bob);
+        assert_eq!(hub.client_count(), 1);
+        assert_eq!(hub.members("rust"), vec![alice.id()]);
+        assert_eq!(
+            hub.send(bob_id, "gone".to_string()).await,
+            Err(HubError::ClientNotFound(bob_id))
+        );
+        assert!(!hub.join(bob_id, "rust"));
+
+        hub.disconnect(alice.id());
+        assert_eq!(alice.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_hub_backpressure() {
+        let hub = Hub::with_capacity(2);
+        let mut slow = hub.subscribe();
+        let mut fast = hub.subscribe();
+
+        for n in 0..2 {
+            assert_eq!(hub.broadcast(n), 2);
+        }
+        assert_eq!(fast.recv().await, Some(0));
+        assert_eq!(fast.recv().await, Some(1));
+
+        // The slow subscriber is disconnected, after receiving what it buffered
+        assert_eq!(hub.broadcast(2), 1);
+        assert_eq!(hub.client_count(), 1);
+        let received: Vec<i32> = (&mut slow).collect().await;
+        assert_eq!(received, vec![0, 1]);
+        assert_eq!(fast.recv().await, Some(2));
+
+        let hub = Hub::with_capacity(1).backpressure(Backpressure::DropMessage);
+        let mut slow = hub.subscribe();
+        assert_eq!(hub.broadcast(1), 1);
+        assert_eq!(hub.broadcast(2), 0);
+        assert_eq!(hub.client_count(), 1);
+        assert_eq!(slow.recv().await, Some(1));
+        assert_eq!(hub.broadcast(3), 1);
+        assert_eq!(slow.recv().await, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_hub_from_state() {
+        async fn publish(req: HttpRequest, State(hub): State<Hub<String>>) -> String {
+            let room = req.get_params("room").unwrap();
+            format!("{}", hub.broadcast_to(room, format!("posted in {}", room)))
+        }
+
+        let hub = Hub::<String>::new();
+        let mut subscription = hub.subscribe();
+        subscription.join("news");
+
+        let mut app = App::new();
+        app.with_state(hub.clone());
+        app.post("/rooms/{room}", handler(publish));
+        let table = app.build_dispatch_table().unwrap();
+
+        let (req, mut payload) = actix_web::test::TestRequest::post()
+            .uri("/rooms/news")
+            .to_http_parts();
+        let payload = {
+            use actix_web::FromRequest;
+            actix_web::web::Payload::from_request(&req, &mut payload)
+                .await
+                .unwrap()
+        };
+        let response = table.dispatch(req, payload).await;
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "1");
+        assert_eq!(subscription.recv().await.unwrap(), "posted in news");
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use crate::types::MediaType;
+
+    #[test]
+    fn test_parse_media_type() {
+        let media_type = MediaType::parse("text/html").unwrap();
+        assert_eq!(media_type.essence(), "text/html");
+        assert_eq!(media_type.type_(), "text");
+        assert_eq!(media_type.subtype(), "html");
+        assert_eq!(media_type.suffix(), None);
+        assert_eq!(media_type.charset(), None);
+
+        let media_type = MediaType::parse(" Text/HTML ; Charset=UTF-8 ").unwrap();
+        assert_eq!(media_type.essence(), "text/html");
+        assert_eq!(media_type.charset(), Some("UTF-8"));
+        assert_eq!(media_type.param("CHARSET"), Some("UTF-8"));
+
+        assert_eq!(MediaType::parse(""), None);
+        assert_eq!(MediaType::parse("text"), None);
+        assert_eq!(MediaType::parse("text/"), None);
+        assert_eq!(MediaType::parse("te xt/html"), None);
+    }
+
+    #[test]
+    fn test_media_type_params() {
+        let media_type =
+            MediaType::parse(r#"multipart/form-data; boundary="a;b \"c\""; invalid; x=1"#).unwrap();
+        assert_eq!(media_type.param("boundary"), Some(r#"a;b "c""#));
+        assert_eq!(media_type.param("x"), Some("1"));
+        assert_eq!(media_type.param("invalid"), None);
+        assert_eq!(
+            media_type.to_string(),
+            r#"multipart/form-data; boundary="a;b \"c\""; x=1"#
+        );
+    }
+
+    #[test]
+    fn test_json_media_types() {
+        assert!(MediaType::parse("application/json").unwrap().is_json());
+        assert!(MediaType::parse("application/vnd.api+json")
+            .unwrap()
+            .is_json());
+        assert!(MediaType::parse("APPLICATION/LD+JSON; charset=utf-8")
+            .unwrap()
+            .is_json());
+        assert!(!MediaType::parse("application/jsonp").unwrap().is_json());
+        assert!(!MediaType::parse("text/plain").unwrap().is_json());
+    }
+}
@@ -1,15 +1,22 @@
 #[cfg(test)]
 mod tests {
 
-    use std::sync::Arc;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
 
     use crate::{
+        app::App,
         context::{HttpRequest, HttpResponse},
         middlewares::{
+            compose,
             cors::{cors, CorsConfig},
+            from_fn,
             logger::{logger, LoggerConfig},
+            unless, when,
         },
-        types::Next,
+        types::{AsyncMiddleware, Fut, Middleware, MiddlewarePath, Next, RequestBodyType},
     };
 
     #[tokio::test]
@@ -121,4 +128,161 @@ mod tests {
 
         let _ = logger_mw(req, res, next).await;
     }
+
+    #[test]
+    fn test_middleware_path_segments() {
+        let path = MiddlewarePath::new("/auth");
+
+        assert!(path.matches("/auth"));
+        assert!(path.matches("/auth/"));
+        assert!(path.matches("/auth/login"));
+        assert!(!path.matches("/authors"));
+        assert!(!path.matches("/"));
+
+        assert!(MiddlewarePath::new("/").matches("/anything"));
+        assert!(MiddlewarePath::new("").matches("/anything"));
+        assert!(MiddlewarePath::from(None).matches("/anything"));
+    }
+
+    #[test]
+    fn test_middleware_path_globs() {
+        let path = MiddlewarePath::new("/api/*/admin");
+
+        assert!(path.matches("/api/v1/admin"));
+        assert!(path.matches("/api/v2/admin/users"));
+        assert!(!path.matches("/api/admin"));
+        assert!(!path.matches("/api/v1/v2/admin"));
+
+        let path = MiddlewarePath::new("/api/**/admin");
+        assert!(path.matches("/api/admin"));
+        assert!(path.matches("/api/v1/v2/admin/users"));
+        assert!(!path.matches("/api/v1/users"));
+    }
+
+    #[test]
+    fn test_middleware_path_exclude() {
+        let path = MiddlewarePath::new("/api")
+            .exclude("/api/login")
+            .exclude("/api/*/public");
+
+        assert!(path.matches("/api/users"));
+        assert!(!path.matches("/api/login"));
+        assert!(!path.matches("/api/login/callback"));
+        assert!(path.matches("/api/loginx"));
+        assert!(!path.matches("/api/v1/public"));
+    }
+
+    #[derive(Clone, Default)]
+    struct Counter {
+        count: Arc<AtomicUsize>,
+    }
+
+    impl AsyncMiddleware for Counter {
+        async fn handle(&self, req: HttpRequest, res: HttpResponse, next: Next) -> HttpResponse {
+            let count = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+            next.run(req, res)
+                .await
+                .set_header("X-Count", count.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_use_layer_async_middleware() {
+        let counter = Counter::default();
+        let mut app = App::new();
+        app.use_layer(counter.clone());
+
+        for expected in 1..=2 {
+            let next = Next {
+                middleware: app.get_middlewares().clone(),
+                handler: Arc::new(|_req, res| Box::pin(async { res })),
+            };
+            let response = next.run(HttpRequest::new(), HttpResponse::new()).await;
+            assert_eq!(
+                response.get_header("X-Count").unwrap(),
+                expected.to_string()
+            );
+        }
+
+        assert_eq!(counter.count.load(Ordering::SeqCst), 2);
+    }
+
+    struct Deny;
+
+    impl Middleware for Deny {
+        fn handle(&self, _req: HttpRequest, res: HttpResponse, _next: Next) -> Fut {
+            Box::pin(async move { res.unauthorized() })
+        }
+
+        fn clone_box(&self) -> Box<dyn Middleware> {
+            Box::new(Deny)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_use_layer_middleware() {
+        let mut app = App::new();
+        app.use_layer(Deny);
+
+        let next = Next {
+            middleware: app.get_middlewares().clone(),
+            handler: Arc::new(|_req, res| Box::pin(async { res.ok() })),
+        };
+        let response = next.run(HttpRequest::new(), HttpResponse::new()).await;
+        assert_eq!(response.get_status_code(), 401);
+    }
+
+    fn tag(name: &'static str) -> impl Middleware {
+        from_fn(
+            move |req: HttpRequest, res: HttpResponse, next: Next| async move {
+                let res = next.run(req, res).await;
+                let tags = match res.get_header("X-Tags") {
+                    Ok(tags) => format!("{},{}", name, tags),
+                    Err(_) => name.to_string(),
+                };
+                res.set_header("X-Tags", &tags)
+            },
+        )
+    }
+
+    fn run_chain(middleware: Vec<Box<dyn Middleware>>, req: HttpRequest) -> Fut {
+        let next = Next {
+            middleware,
+            handler: Arc::new(|_req, res| Box::pin(async { res })),
+        };
+        Box::pin(next.run(req, HttpResponse::new()))
+    }
+
+    #[tokio::test]
+    async fn test_when_and_unless() {
+        let middleware: Vec<Box<dyn Middleware>> = vec![
+            Box::new(when(|req| req.is(RequestBodyType::JSON), tag("json"))),
+            Box::new(unless(
+                |req| req.get_header("X-Skip").is_ok(),
+                tag("unless"),
+            )),
+        ];
+
+        let mut req = HttpRequest::new();
+        req.set_content_type(RequestBodyType::JSON);
+        let response = run_chain(middleware.clone(), req).await;
+        assert_eq!(response.get_header("X-Tags").unwrap(), "json,unless");
+
+        let mut req = HttpRequest::new();
+        req.set_header("x-skip", "1");
+        let response = run_chain(middleware, req).await;
+        assert!(response.get_header("X-Tags").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compose() {
+        let composed = compose([
+            Box::new(tag("first")) as Box<dyn Middleware>,
+            Box::new(when(|_| true, tag("second"))),
+        ]);
+        let middleware: Vec<Box<dyn Middleware>> = vec![Box::new(composed), Box::new(tag("last"))];
+
+        let response = run_chain(middleware, HttpRequest::new()).await;
+        assert_eq!(response.get_header("X-Tags").unwrap(), "first,second,last");
+    }
 }
@@ -1,4 +1,12 @@
 mod app_test;
+mod extract_test;
+mod headers_test;
+mod hub_test;
+mod media_type_test;
 mod middleware_tests;
+mod multipart_test;
 mod request_test;
 mod response_test;
+mod router_test;
+mod sse_test;
+mod ws_test;
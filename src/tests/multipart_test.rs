@@ -0,0 +1,314 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::FromRequest;
+
+    use crate::app::App;
+    use crate::context::{HttpRequest, HttpResponse};
+    use crate::types::{handler, MultipartConfig, MultipartStream, RequestBodyType, RipressError};
+    use futures_util::StreamExt;
+
+    const BOUNDARY: &str = "ripress-boundary";
+
+    fn multipart_body(parts: &[(&str, Option<&str>, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (name, file_name, content) in parts {
+            body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+            match file_name {
+                Some(file_name) => body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+                         Content-Type: application/octet-stream\r\n\r\n",
+                        name, file_name
+                    )
+                    .as_bytes(),
+                ),
+                None => body.extend_from_slice(
+                    format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes(),
+                ),
+            }
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+        body
+    }
+
+    fn multipart_request(body: Vec<u8>) -> actix_web::test::TestRequest {
+        actix_web::test::TestRequest::post()
+            .uri("/upload")
+            .insert_header((
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            ))
+            .set_payload(body)
+    }
+
+    async fn read_request(
+        body: Vec<u8>,
+        limit: usize,
+        config: &MultipartConfig,
+    ) -> Result<HttpRequest, RipressError> {
+        let (request, mut payload) = multipart_request(body).to_http_parts();
+        let web_payload = actix_web::web::Payload::from_request(&request, &mut payload)
+            .await
+            .unwrap();
+
+        let mut req = HttpRequest::from_actix_parts(&request);
+        req.read_body(web_payload, limit, config).await?;
+        Ok(req)
+    }
+
+    #[tokio::test]
+    async fn test_multipart_fields_and_files() {
+        let large = vec![7u8; 1024];
+        let body = multipart_body(&[
+            ("title", None, b"Holiday"),
+            ("small", Some("small.bin"), b"\x00\x01\x02"),
+            ("large", Some("large.bin"), &large),
+        ]);
+        let config = MultipartConfig {
+            memory_threshold: 512,
+            ..MultipartConfig::default()
+        };
+
+        let req = read_request(body, 1024 * 1024, &config).await.unwrap();
+        assert!(req.is(RequestBodyType::MULTIPART));
+
+        let form = req.multipart().unwrap();
+        assert_eq!(form.field("title"), Some("Holiday"));
+        assert_eq!(
+            req.form_data().unwrap().get("title"),
+            Some(&String::from("Holiday"))
+        );
+        assert_eq!(req.files().len(), 2);
+
+        let small = form.file("small").unwrap();
+        assert_eq!(small.field_name(), "small");
+        assert_eq!(small.file_name(), Some("small.bin"));
+        assert_eq!(small.content_type(), Some("application/octet-stream"));
+        assert_eq!(small.size(), 3);
+        assert!(small.path().is_none());
+        assert_eq!(&small.bytes().await.unwrap()[..], b"\x00\x01\x02");
+
+        let large = form.file("large").unwrap();
+        assert_eq!(large.size(), 1024);
+        let path = large.path().unwrap().to_path_buf();
+        assert!(path.exists());
+        assert_eq!(large.bytes().await.unwrap(), vec![7u8; 1024]);
+
+        let copy = tempfile::NamedTempFile::new().unwrap();
+        large.save_to(copy.path()).await.unwrap();
+        assert_eq!(std::fs::read(copy.path()).unwrap(), vec![7u8; 1024]);
+
+        drop(req);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_limits() {
+        let config = MultipartConfig {
+            max_file_size: 8,
+            ..MultipartConfig::default()
+        };
+
+        let body = multipart_body(&[("file", Some("a.bin"), b"0123456789")]);
+        let err = read_request(body, 1024, &config).await.unwrap_err();
+        assert!(matches!(err, RipressError::PayloadTooLarge(8)));
+
+        let body = multipart_body(&[("text", None, b"0123456789")]);
+        assert!(read_request(body, 1024, &config).await.is_ok());
+
+        let body = multipart_body(&[("text", None, b"0123456789")]);
+        let err = read_request(body, 5, &config).await.unwrap_err();
+        assert!(matches!(err, RipressError::PayloadTooLarge(5)));
+
+        let config = MultipartConfig {
+            max_field_size: 8,
+            ..MultipartConfig::default()
+        };
+        let body = multipart_body(&[("text", None, b"0123456789")]);
+        let err = read_request(body, 1024, &config).await.unwrap_err();
+        assert!(matches!(err, RipressError::PayloadTooLarge(8)));
+        assert_eq!(err.status_code(), 413);
+
+        let body = multipart_body(&[("file", Some("a.bin"), b"0123456789")]);
+        assert!(read_request(body, 1024, &config).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_invalid_body() {
+        let (request, mut payload) = actix_web::test::TestRequest::post()
+            .insert_header(("Content-Type", "multipart/form-data"))
+            .set_payload("no boundary")
+            .to_http_parts();
+        let web_payload = actix_web::web::Payload::from_request(&request, &mut payload)
+            .await
+            .unwrap();
+
+        let mut req = HttpRequest::from_actix_parts(&request);
+        let err = req
+            .read_body(web_payload, 1024, &MultipartConfig::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_route() {
+        async fn upload(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+            let names: Vec<&str> = req.files().iter().filter_map(|f| f.file_name()).collect();
+            res.ok().text(names.join(","))
+        }
+
+        let mut app = App::new();
+        app.post("/upload", upload);
+        let table = app.build_dispatch_table().unwrap();
+
+        let body = multipart_body(&[
+            ("a", Some("a.txt"), b"first"),
+            ("b", Some("b.txt"), b"second"),
+        ]);
+        let (req, mut payload) = multipart_request(body).to_http_parts();
+        let payload = actix_web::web::Payload::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+
+        let response = table.dispatch(req, payload).await;
+        assert_eq!(response.status(), 200);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "a.txt,b.txt");
+    }
+
+    async fn dispatch_multipart(
+        table: &crate::app::DispatchTable,
+        body: Vec<u8>,
+    ) -> (u16, actix_web::web::Bytes) {
+        let (req, mut payload) = multipart_request(body).to_http_parts();
+        let payload = actix_web::web::Payload::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+
+        let response = table.dispatch(req, payload).await;
+        let status = response.status().as_u16();
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_multipart_stream() {
+        async fn upload(mut form: MultipartStream, res: HttpResponse) -> HttpResponse {
+            let mut received = Vec::new();
+            while let Some(field) = form.next_field().await {
+                let mut field = match field {
+                    Ok(field) => field,
+                    Err(err) => return res.error(err),
+                };
+                match field.name() {
+                    "title" => received.push(format!("title={}", field.text().await.unwrap())),
+                    "skipped" => received.push(String::from("skipped")),
+                    _ => {
+                        let mut size = 0;
+                        while let Some(chunk) = field.next().await {
+                            match chunk {
+                                Ok(chunk) => size += chunk.len(),
+                                Err(err) => return res.error(err),
+                            }
+                        }
+                        received.push(format!("{}:{}", field.file_name().unwrap(), size));
+                    }
+                }
+            }
+            res.ok().text(received.join(","))
+        }
+
+        let mut app = App::new();
+        app.post("/upload", handler(upload)).stream_body();
+        app.multipart_config(MultipartConfig {
+            max_file_size: 2048,
+            ..MultipartConfig::default()
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        let large = vec![7u8; 2000];
+        let body = multipart_body(&[
+            ("title", None, b"Holiday"),
+            ("skipped", Some("skipped.bin"), &large),
+            ("photo", Some("photo.bin"), &large),
+        ]);
+        let (status, body) = dispatch_multipart(&table, body).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "title=Holiday,skipped,photo.bin:2000");
+
+        let too_large = vec![7u8; 4096];
+        let body = multipart_body(&[("photo", Some("photo.bin"), &too_large)]);
+        let (status, _) = dispatch_multipart(&table, body).await;
+        assert_eq!(status, 413);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_stream_rejects_fields() {
+        async fn upload(req: HttpRequest, res: HttpResponse) -> HttpResponse {
+            let mut form = req.multipart_stream().unwrap();
+            assert!(req.multipart_stream().is_none());
+            assert!(req.body_stream().is_none());
+
+            while let Some(Ok(field)) = form.next_field().await {
+                if field.file_name().is_some() {
+                    return res
+                        .bad_request()
+                        .text(format!("Unexpected file in {}", field.name()));
+                }
+            }
+            res.ok().text("No files")
+        }
+
+        let mut app = App::new();
+        app.post("/upload", upload).stream_body();
+        let table = app.build_dispatch_table().unwrap();
+
+        // Rejected before the rest of the body is read
+        let large = vec![7u8; 64 * 1024];
+        let body = multipart_body(&[
+            ("avatar", Some("avatar.bin"), &large),
+            ("title", None, b"Holiday"),
+        ]);
+        let (status, body) = dispatch_multipart(&table, body).await;
+        assert_eq!(status, 400);
+        assert_eq!(body, "Unexpected file in avatar");
+
+        let body = multipart_body(&[("title", None, b"Holiday")]);
+        let (status, body) = dispatch_multipart(&table, body).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "No files");
+    }
+
+    #[tokio::test]
+    async fn test_multipart_stream_extractor() {
+        async fn upload(_form: MultipartStream, res: HttpResponse) -> HttpResponse {
+            res.ok().text("multipart")
+        }
+
+        let mut app = App::new();
+        app.post("/upload", handler(upload)).stream_body();
+        let table = app.build_dispatch_table().unwrap();
+
+        let (req, mut payload) = actix_web::test::TestRequest::post()
+            .uri("/upload")
+            .insert_header(("Content-Type", "text/plain"))
+            .set_payload("not a form")
+            .to_http_parts();
+        let payload = actix_web::web::Payload::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+        assert_eq!(table.dispatch(req, payload).await.status(), 415);
+
+        let body = multipart_body(&[("title", None, b"Holiday")]);
+        let (status, body) = dispatch_multipart(&table, body).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "multipart");
+    }
+}
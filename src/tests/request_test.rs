@@ -2,6 +2,7 @@
 mod tests {
     use actix_web::FromRequest;
     use serde_json::json;
+    use std::collections::HashMap;
 
     use crate::types::HttpRequestError;
     use crate::{
@@ -262,6 +263,27 @@ mod tests {
 
         let content_type = determine_content_type("application/x-www-form-urlencoded");
         assert_eq!(content_type, RequestBodyType::FORM);
+
+        let content_type = determine_content_type("image/png");
+        assert_eq!(content_type, RequestBodyType::BINARY);
+
+        let content_type = determine_content_type("application/octet-stream");
+        assert_eq!(content_type, RequestBodyType::BINARY);
+
+        let content_type = determine_content_type("application/json; charset=utf-8");
+        assert_eq!(content_type, RequestBodyType::JSON);
+
+        let content_type = determine_content_type("application/vnd.api+json");
+        assert_eq!(content_type, RequestBodyType::JSON);
+
+        let content_type = determine_content_type("Application/X-WWW-Form-Urlencoded");
+        assert_eq!(content_type, RequestBodyType::FORM);
+
+        let content_type = determine_content_type("multipart/form-data; boundary=abc");
+        assert_eq!(content_type, RequestBodyType::MULTIPART);
+
+        let content_type = determine_content_type("not a media type");
+        assert_eq!(content_type, RequestBodyType::TEXT);
     }
 
     #[test]
@@ -297,4 +319,295 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_from_actix_request_headers() {
+        let request = actix_web::test::TestRequest::default()
+            .insert_header(("X-Custom", "value"))
+            .insert_header((
+                "X-Binary",
+                actix_web::http::header::HeaderValue::from_bytes(b"caf\xe9").unwrap(),
+            ))
+            .to_http_request();
+        let mut payload = actix_web::dev::Payload::None;
+        let web_payload = actix_web::web::Payload::from_request(&request, &mut payload)
+            .await
+            .unwrap();
+
+        let req = HttpRequest::from_actix_request(request, web_payload)
+            .await
+            .unwrap();
+
+        assert_eq!(req.get_header("x-custom").unwrap(), "value");
+        assert_eq!(req.get_header("X-Custom").unwrap(), "value");
+        assert_eq!(
+            req.get_header("x-binary"),
+            Err(HttpRequestError::MissingHeader("x-binary".to_string()))
+        );
+
+        let cloned = req.clone();
+        assert_eq!(cloned.get_header("x-custom").unwrap(), "value");
+    }
+
+    #[tokio::test]
+    async fn test_from_actix_request_repeated_headers() {
+        let request = actix_web::test::TestRequest::default()
+            .append_header(("Accept", "text/html"))
+            .append_header(("Accept", "application/json"))
+            .append_header((
+                "X-Binary",
+                actix_web::http::header::HeaderValue::from_bytes(b"caf\xe9").unwrap(),
+            ))
+            .to_http_request();
+        let mut payload = actix_web::dev::Payload::None;
+        let web_payload = actix_web::web::Payload::from_request(&request, &mut payload)
+            .await
+            .unwrap();
+
+        let req = HttpRequest::from_actix_request(request, web_payload)
+            .await
+            .unwrap();
+
+        assert_eq!(req.get_header("accept").unwrap(), "text/html");
+        assert_eq!(
+            req.headers().get_all("ACCEPT"),
+            vec!["text/html", "application/json"]
+        );
+        assert_eq!(req.headers().get_bytes("x-binary"), Some(&b"caf\xe9"[..]));
+    }
+
+    async fn request_with_body(content_type: &str, body: &'static [u8]) -> HttpRequest {
+        let (request, mut payload) = actix_web::test::TestRequest::post()
+            .insert_header(("Content-Type", content_type))
+            .set_payload(body)
+            .to_http_parts();
+        let web_payload = actix_web::web::Payload::from_request(&request, &mut payload)
+            .await
+            .unwrap();
+
+        HttpRequest::from_actix_request(request, web_payload)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_binary_body() {
+        let req = request_with_body("image/png", b"\x89PNG\r\n\x1a\n").await;
+        assert!(req.is(RequestBodyType::BINARY));
+        assert_eq!(&req.bytes()[..], b"\x89PNG\r\n\x1a\n");
+        assert!(req.text().is_err());
+
+        let req = request_with_body("text/plain", b"caf\xe9").await;
+        assert!(req.is(RequestBodyType::BINARY));
+        assert_eq!(&req.bytes()[..], b"caf\xe9");
+
+        let req = request_with_body("text/plain", b"hello").await;
+        assert_eq!(req.text().unwrap(), "hello");
+        assert_eq!(&req.bytes()[..], b"hello");
+
+        let req = request_with_body("application/json", b"{ \"name\": \"John\" }").await;
+        assert_eq!(
+            req.json::<serde_json::Value>().unwrap(),
+            json!({"name": "John"})
+        );
+        assert_eq!(&req.bytes()[..], b"{ \"name\": \"John\" }");
+
+        let req = request_with_body("application/x-www-form-urlencoded", b"a=1&b=2").await;
+        assert_eq!(req.form_data().unwrap().get("a"), Some(&String::from("1")));
+        assert_eq!(&req.bytes()[..], b"a=1&b=2");
+    }
+
+    #[tokio::test]
+    async fn test_body_charset() {
+        let req = request_with_body("text/plain; charset=ISO-8859-1", b"caf\xe9").await;
+        assert!(req.is(RequestBodyType::TEXT));
+        assert_eq!(req.text().unwrap(), "café");
+        assert_eq!(req.media_type().unwrap().charset(), Some("ISO-8859-1"));
+
+        let req = request_with_body(
+            "application/x-www-form-urlencoded; charset=latin1",
+            b"name=Jos\xe9",
+        )
+        .await;
+        assert_eq!(
+            req.form_data().unwrap().get("name"),
+            Some(&String::from("José"))
+        );
+
+        let req = request_with_body(
+            "application/problem+json; charset=windows-1252",
+            b"{\"name\": \"Jos\xe9\"}",
+        )
+        .await;
+        assert_eq!(
+            req.json::<serde_json::Value>().unwrap(),
+            json!({"name": "José"})
+        );
+
+        let (request, mut payload) = actix_web::test::TestRequest::post()
+            .insert_header(("Content-Type", "text/plain; charset=klingon"))
+            .set_payload("hello")
+            .to_http_parts();
+        let web_payload = actix_web::web::Payload::from_request(&request, &mut payload)
+            .await
+            .unwrap();
+        let err = HttpRequest::from_actix_request(request, web_payload)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 415);
+
+        let req = request_with_body("image/png; charset=foo", b"\x89PNG").await;
+        assert!(req.is(RequestBodyType::BINARY));
+        assert_eq!(&req.bytes()[..], b"\x89PNG");
+    }
+
+    #[test]
+    fn test_typed_query() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Order {
+            Asc,
+            Desc,
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Search {
+            q: String,
+            page: Option<u32>,
+            #[serde(default)]
+            per_page: u32,
+            #[serde(default)]
+            tag: Vec<String>,
+            exact: Option<bool>,
+            order: Option<Order>,
+        }
+
+        let mut req = HttpRequest::new();
+        req.set_query("q", "rust lang");
+        req.set_query("tag", "web");
+        req.set_query("tag", "http");
+        req.set_query("page", "");
+        req.set_query("order", "desc");
+
+        assert_eq!(
+            req.query::<Search>().unwrap(),
+            Search {
+                q: "rust lang".to_string(),
+                page: None,
+                per_page: 0,
+                tag: vec!["web".to_string(), "http".to_string()],
+                exact: None,
+                order: Some(Order::Desc),
+            }
+        );
+
+        req.set_query("exact", "maybe");
+        let err = req.query::<Search>().unwrap_err();
+        assert_eq!(err.field(), Some("exact"));
+        assert_eq!(
+            err.to_string(),
+            "Invalid query parameter exact: provided string was not `true` or `false`"
+        );
+
+        let err = HttpRequest::new().query::<Search>().unwrap_err();
+        assert_eq!(err.field(), Some("q"));
+        assert_eq!(err.message(), "missing field");
+
+        let map = req.query::<HashMap<String, String>>().unwrap();
+        assert_eq!(map.get("q").unwrap(), "rust lang");
+    }
+
+    #[tokio::test]
+    async fn test_multi_valued_query() {
+        let request = actix_web::test::TestRequest::default()
+            .uri("/posts?tag=rust&tag=web&tag[]=http&page=1&page=2")
+            .to_http_request();
+        let mut payload = actix_web::dev::Payload::None;
+        let web_payload = actix_web::web::Payload::from_request(&request, &mut payload)
+            .await
+            .unwrap();
+        let req = HttpRequest::from_actix_request(request, web_payload)
+            .await
+            .unwrap();
+
+        assert_eq!(req.get_query_all("tag"), vec!["rust", "web", "http"]);
+        assert_eq!(req.get_query_all("missing"), Vec::<&str>::new());
+        assert_eq!(req.get_query("page"), Ok("2"));
+        assert_eq!(
+            req.query_string(),
+            "tag=rust&tag=web&tag[]=http&page=1&page=2"
+        );
+    }
+
+    #[test]
+    fn test_nested_query() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Filter {
+            status: String,
+            min_price: Option<u32>,
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Item {
+            id: u32,
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Search {
+            filter: Filter,
+            tag: Vec<String>,
+            #[serde(default)]
+            items: Vec<Item>,
+        }
+
+        let mut req = HttpRequest::new();
+        req.set_query("filter[status]", "open");
+        req.set_query("tag[]", "a");
+        req.set_query("tag[]", "b");
+        req.set_query("items[1][id]", "2");
+        req.set_query("items[0][id]", "1");
+
+        assert_eq!(
+            req.query::<Search>().unwrap(),
+            Search {
+                filter: Filter {
+                    status: "open".to_string(),
+                    min_price: None,
+                },
+                tag: vec!["a".to_string(), "b".to_string()],
+                items: vec![Item { id: 1 }, Item { id: 2 }],
+            }
+        );
+
+        req.set_query("filter[min_price]", "cheap");
+        let err = req.query::<Search>().unwrap_err();
+        assert_eq!(err.field(), Some("filter[min_price]"));
+
+        let mut req = HttpRequest::new();
+        req.set_query("filter[status]", "open");
+        req.set_query("filter", "open");
+        let err = req.query::<HashMap<String, String>>().unwrap_err();
+        assert_eq!(err.field(), Some("filter"));
+
+        let mut req = HttpRequest::new();
+        req.set_query("filter[status", "open");
+        let map = req.query::<HashMap<String, String>>().unwrap();
+        assert_eq!(map.get("filter[status").unwrap(), "open");
+
+        // Brackets past the maximum depth are kept as one segment
+        let mut req = HttpRequest::new();
+        req.set_query(&format!("deep{}", "[x]".repeat(2000)), "1");
+        req.set_query("filter[status]", "open");
+        req.set_query("tag", "a");
+        let search = req.query::<Search>().unwrap();
+        assert_eq!(search.filter.status, "open");
+
+        let mut req = HttpRequest::new();
+        req.set_query(&format!("deep{}", "[x]".repeat(18)), "1");
+        let mut node = req.query::<serde_json::Value>().unwrap()["deep"].take();
+        for _ in 0..16 {
+            node = node["x"].take();
+        }
+        assert_eq!(node, json!({ "[x][x]": "1" }));
+    }
 }
@@ -1,8 +1,12 @@
 #[cfg(test)]
 mod tests {
+    use crate::extract::Json;
     use crate::response::HttpResponse;
     use crate::types::HttpResponseError::MissingHeader;
-    use crate::types::{HttpResponseError, ResponseContentBody, ResponseContentType};
+    use crate::types::{
+        Html, HttpRequestError, HttpResponseError, IntoResponse, ResponseContentBody,
+        ResponseContentType, RipressError,
+    };
     use actix_web::Responder;
     use serde_json::json;
 
@@ -145,6 +149,110 @@ mod tests {
         let response = HttpResponse::new();
         let response = response.set_content_type(ResponseContentType::JSON);
         assert_eq!(response.get_content_type(), ResponseContentType::JSON);
+
+        let actix_response = HttpResponse::new()
+            .ok()
+            .text("<note/>")
+            .set_content_type(ResponseContentType::XML)
+            .to_responder();
+        assert_eq!(
+            actix_response.headers().get("content-type").unwrap(),
+            "application/xml"
+        );
+    }
+
+    #[test]
+    fn test_content_type() {
+        let response = HttpResponse::new()
+            .ok()
+            .text("body { color: red; }")
+            .content_type("TEXT/CSS");
+        assert_eq!(response.get_content_type(), ResponseContentType::CSS);
+
+        let response = HttpResponse::new()
+            .ok()
+            .text("id,name\n1,Alice\n")
+            .content_type("text/csv; charset=utf-8");
+        assert_eq!(
+            response.get_content_type(),
+            ResponseContentType::CUSTOM("text/csv; charset=utf-8".to_string())
+        );
+        let actix_response = response.to_responder();
+        assert_eq!(
+            actix_response.headers().get("content-type").unwrap(),
+            "text/csv; charset=utf-8"
+        );
+
+        let response = HttpResponse::new().ok().content_type("text/plain\n");
+        assert!(response.get_error().is_some());
+
+        assert_eq!(
+            ResponseContentType::from("text/javascript"),
+            ResponseContentType::JAVASCRIPT
+        );
+        assert_eq!(
+            ResponseContentType::from("application/octet-stream"),
+            ResponseContentType::BINARY
+        );
+        assert_eq!(ResponseContentType::XML.to_string(), "application/xml");
+    }
+
+    #[test]
+    fn test_manual_content_type_header() {
+        let actix_response = HttpResponse::new()
+            .ok()
+            .set_header("Content-Type", "application/vnd.api+json")
+            .json(json!({"data": []}))
+            .to_responder();
+        let content_types: Vec<_> = actix_response.headers().get_all("content-type").collect();
+        assert_eq!(content_types, vec!["application/vnd.api+json"]);
+
+        // Applies to the file, not to the fallback response of a file that can't be sent
+        let file = temp_file(".bin", "0123456789");
+        let send = || {
+            HttpResponse::new()
+                .ok()
+                .set_header("content-type", "application/x-custom")
+                .send_file(file.path())
+        };
+        let req = actix_web::test::TestRequest::get().to_http_request();
+        let actix_response = send().respond_to(&req);
+        let content_types: Vec<_> = actix_response.headers().get_all("content-type").collect();
+        assert_eq!(content_types, vec!["application/x-custom"]);
+
+        let req = actix_web::test::TestRequest::get()
+            .insert_header(("Range", "bytes=20-30"))
+            .to_http_request();
+        let actix_response = send().respond_to(&req);
+        assert_eq!(actix_response.status(), 416);
+        let content_types: Vec<_> = actix_response.headers().get_all("content-type").collect();
+        assert_eq!(content_types, vec!["text/plain"]);
+    }
+
+    #[actix_web::test]
+    async fn test_bytes_response() {
+        let response = HttpResponse::new().ok().bytes(vec![0x25, 0x50, 0x44, 0x46]);
+        assert_eq!(response.get_body_size(), 4);
+        assert_eq!(response.get_content_type(), ResponseContentType::BINARY);
+        let actix_response = response.to_responder();
+        assert_eq!(
+            actix_response.headers().get("content-type").unwrap(),
+            "application/octet-stream"
+        );
+
+        let actix_response = HttpResponse::new()
+            .ok()
+            .bytes(b"%PDF".to_vec())
+            .content_type("application/pdf")
+            .to_responder();
+        assert_eq!(
+            actix_response.headers().get("content-type").unwrap(),
+            "application/pdf"
+        );
+        let body = actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "%PDF");
     }
     #[test]
     fn test_clear_cookie() {
@@ -200,4 +308,319 @@ mod tests {
 
         assert_eq!(responder.status(), 401);
     }
+
+    #[test]
+    fn test_response_read_api() {
+        let response = HttpResponse::new()
+            .not_found()
+            .set_header("X-Custom", "value")
+            .json(json!({"key": "value"}));
+
+        assert_eq!(response.get_status_code(), 404);
+        assert_eq!(response.get_content_type(), ResponseContentType::JSON);
+        assert_eq!(response.get_headers().get("X-Custom").unwrap(), "value");
+        assert_eq!(response.get_body_size(), r#"{"key":"value"}"#.len());
+
+        let response = HttpResponse::new().html("<p>hi</p>");
+        assert_eq!(response.get_body_size(), 9);
+    }
+
+    #[test]
+    fn test_error_response() {
+        let response = HttpResponse::new().error(RipressError::Status(409, "Conflict".to_string()));
+        assert_eq!(response.get_error().unwrap().status_code(), 409);
+
+        let actix_response = response.to_responder();
+        assert_eq!(actix_response.status(), 409);
+
+        let err = RipressError::from(HttpRequestError::MissingParam("id".to_string()));
+        assert_eq!(err.status_code(), 400);
+        assert_eq!(err.to_string(), "Param id doesn't exist");
+        assert!(err.downcast_ref::<HttpRequestError>().is_none());
+
+        let err = RipressError::other("database down");
+        assert_eq!(err.status_code(), 500);
+        assert_eq!(err.to_string(), "database down");
+    }
+
+    #[test]
+    fn test_into_response() {
+        let res = "Hello".into_response(HttpResponse::new().set_header("X-Kept", "yes"));
+        assert_eq!(res.get_content_type(), ResponseContentType::TEXT);
+        assert_eq!(res.get_header("X-Kept").unwrap(), "yes");
+        assert_eq!(
+            res.get_body(),
+            ResponseContentBody::TEXT("Hello".to_string())
+        );
+
+        let res = String::from("Hello").into_response(HttpResponse::new());
+        assert_eq!(
+            res.get_body(),
+            ResponseContentBody::TEXT("Hello".to_string())
+        );
+
+        let res = json!({"id": 1}).into_response(HttpResponse::new());
+        assert_eq!(res.get_body(), ResponseContentBody::JSON(json!({"id": 1})));
+
+        let res = Json(vec![1, 2]).into_response(HttpResponse::new());
+        assert_eq!(res.get_body(), ResponseContentBody::JSON(json!([1, 2])));
+
+        let res = (201, Html("<p>Hi</p>")).into_response(HttpResponse::new());
+        assert_eq!(res.get_status_code(), 201);
+        assert_eq!(res.get_content_type(), ResponseContentType::HTML);
+
+        let res =
+            (404, HttpResponse::new().ok().text("Missing")).into_response(HttpResponse::new());
+        assert_eq!(res.get_status_code(), 404);
+
+        let ok: Result<&str, RipressError> = Ok("Fine");
+        assert!(ok.into_response(HttpResponse::new()).get_error().is_none());
+
+        let err: Result<&str, HttpRequestError> =
+            Err(HttpRequestError::MissingParam("id".to_string()));
+        let res = err.into_response(HttpResponse::new());
+        assert_eq!(res.get_error().unwrap().status_code(), 400);
+    }
+
+    #[test]
+    fn test_multi_valued_headers() {
+        let response = HttpResponse::new()
+            .append_header("Link", "</style.css>; rel=preload")
+            .append_header("link", "</app.js>; rel=preload")
+            .set_header("X-Remove", "yes")
+            .remove_header("x-remove");
+
+        assert_eq!(
+            response.get_header("LINK").unwrap(),
+            "</style.css>; rel=preload"
+        );
+        assert!(response.get_header("X-Remove").is_err());
+
+        let actix_response = response.to_responder();
+        let links: Vec<_> = actix_response.headers().get_all("link").collect();
+        assert_eq!(links.len(), 2);
+
+        let response = HttpResponse::new().set_header("bad name", "value");
+        assert_eq!(
+            response.get_error().unwrap().to_string(),
+            "Invalid header name bad name"
+        );
+        assert_eq!(response.to_responder().status(), 500);
+    }
+
+    #[tokio::test]
+    async fn test_stream_response() {
+        use actix_web::body::{BodySize, MessageBody};
+        use futures_util::stream;
+
+        let lines = stream::iter(vec![
+            Ok::<_, std::io::Error>("id,name\n"),
+            Ok("1,Alice\n"),
+            Ok("2,Bob\n"),
+        ]);
+        let response = HttpResponse::new().ok().stream(lines);
+        assert_eq!(response.get_body_size(), 0);
+
+        let actix_response = response.to_responder();
+        assert_eq!(actix_response.status(), 200);
+        assert_eq!(
+            actix_response.headers().get("content-type").unwrap(),
+            "application/octet-stream"
+        );
+        assert_eq!(actix_response.body().size(), BodySize::Stream);
+        let body = actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "id,name\n1,Alice\n2,Bob\n");
+
+        let chunks = stream::iter(vec![Ok::<_, std::io::Error>(vec![1u8, 2])]);
+        let actix_response = HttpResponse::new()
+            .set_header("Content-Type", "text/csv")
+            .stream(chunks)
+            .to_responder();
+        let content_types: Vec<_> = actix_response.headers().get_all("content-type").collect();
+        assert_eq!(content_types, vec!["text/csv"]);
+
+        let failing = stream::iter(vec![
+            Ok("partial".to_string()),
+            Err(std::io::Error::other("disk failed")),
+        ]);
+        let actix_response = HttpResponse::new().stream(failing).to_responder();
+        assert!(actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_reader_response() {
+        let data: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+        let reader = std::io::Cursor::new(data.clone());
+
+        let actix_response = HttpResponse::new()
+            .ok()
+            .stream_reader(reader)
+            .to_responder();
+        let body = actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, data);
+    }
+
+    fn temp_file(suffix: &str, contents: &str) -> tempfile::NamedTempFile {
+        use std::io::Write;
+
+        let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[actix_web::test]
+    async fn test_send_file() {
+        let file = temp_file(".txt", "Hello from disk");
+
+        let response = HttpResponse::new().ok().send_file(file.path());
+        assert_eq!(response.get_body_size(), 0);
+        let actix_response = response.to_responder();
+        assert_eq!(actix_response.status(), 200);
+        assert_eq!(
+            actix_response.headers().get("content-type").unwrap(),
+            "text/plain"
+        );
+        let disposition = actix_response.headers().get("content-disposition").unwrap();
+        assert!(disposition.to_str().unwrap().starts_with("inline"));
+        let body = actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "Hello from disk");
+
+        let actix_response = HttpResponse::new()
+            .set_header("Content-Type", "text/markdown")
+            .send_file(file.path())
+            .to_responder();
+        let content_types: Vec<_> = actix_response.headers().get_all("content-type").collect();
+        assert_eq!(content_types, vec!["text/markdown"]);
+
+        let response = HttpResponse::new().send_file("does/not/exist.txt");
+        assert_eq!(response.get_error().unwrap().status_code(), 404);
+        let response = HttpResponse::new().send_file(std::env::temp_dir());
+        assert_eq!(response.get_error().unwrap().status_code(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_download() {
+        let file = temp_file(".csv", "id,name\n");
+
+        let actix_response = HttpResponse::new()
+            .download(file.path(), "report.csv")
+            .to_responder();
+        assert_eq!(
+            actix_response.headers().get("content-disposition").unwrap(),
+            "attachment; filename=\"report.csv\""
+        );
+
+        let actix_response = HttpResponse::new()
+            .download(file.path(), "résumé.csv")
+            .to_responder();
+        assert_eq!(
+            actix_response.headers().get("content-disposition").unwrap(),
+            "attachment; filename=\"r_sum_.csv\"; filename*=UTF-8''r%C3%A9sum%C3%A9.csv"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_send_file_ranges() {
+        let file = temp_file(".txt", "0123456789");
+        let send = |req: &actix_web::HttpRequest| {
+            HttpResponse::new()
+                .ok()
+                .send_file(file.path())
+                .respond_to(req)
+        };
+
+        let req = actix_web::test::TestRequest::get()
+            .insert_header(("Range", "bytes=2-5"))
+            .to_http_request();
+        let actix_response = send(&req);
+        assert_eq!(actix_response.status(), 206);
+        assert_eq!(
+            actix_response.headers().get("content-range").unwrap(),
+            "bytes 2-5/10"
+        );
+        let etag = actix_response.headers().get("etag").unwrap().clone();
+        let body = actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "2345");
+
+        let req = actix_web::test::TestRequest::get()
+            .insert_header(("Range", "bytes=20-30"))
+            .to_http_request();
+        let actix_response = send(&req);
+        assert_eq!(actix_response.status(), 416);
+        assert_eq!(
+            actix_response.headers().get("content-range").unwrap(),
+            "bytes */10"
+        );
+
+        // A stale If-Range gets the whole file
+        let req = actix_web::test::TestRequest::get()
+            .insert_header(("Range", "bytes=2-5"))
+            .insert_header(("If-Range", "\"stale\""))
+            .to_http_request();
+        let actix_response = send(&req);
+        assert_eq!(actix_response.status(), 200);
+
+        let req = actix_web::test::TestRequest::get()
+            .insert_header(("Range", "bytes=2-5"))
+            .insert_header(("If-Range", etag))
+            .to_http_request();
+        assert_eq!(send(&req).status(), 206);
+    }
+
+    #[actix_web::test]
+    async fn test_send_file_errors_are_plain_text() {
+        let content_type = |res: &actix_web::HttpResponse| {
+            let content_types: Vec<_> = res.headers().get_all("content-type").collect();
+            assert_eq!(content_types.len(), 1);
+            content_types[0].to_str().unwrap().to_string()
+        };
+
+        let actix_response = HttpResponse::new()
+            .ok()
+            .send_file("does/not/exist.png")
+            .to_responder();
+        assert_eq!(actix_response.status(), 404);
+        assert_eq!(content_type(&actix_response), "text/plain");
+
+        // Removed after the response was built
+        let file = temp_file(".png", "not really a png");
+        let response = HttpResponse::new().ok().send_file(file.path());
+        drop(file);
+        let actix_response = response.to_responder();
+        assert_eq!(actix_response.status(), 404);
+        assert_eq!(content_type(&actix_response), "text/plain");
+        let body = actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "File not found");
+
+        let file = temp_file(".png", "0123456789");
+        let req = actix_web::test::TestRequest::get()
+            .insert_header(("Range", "bytes=20-30"))
+            .to_http_request();
+        let actix_response = HttpResponse::new()
+            .ok()
+            .send_file(file.path())
+            .respond_to(&req);
+        assert_eq!(actix_response.status(), 416);
+        assert_eq!(content_type(&actix_response), "text/plain");
+
+        let req = actix_web::test::TestRequest::get().to_http_request();
+        let actix_response = HttpResponse::new()
+            .ok()
+            .send_file(file.path())
+            .respond_to(&req);
+        assert_eq!(actix_response.status(), 200);
+        assert_eq!(content_type(&actix_response), "image/png");
+    }
 }
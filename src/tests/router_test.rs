@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod tests {
+    use crate::router::{RouteMatch, Router};
+    use crate::types::{HttpMethods, RouterError};
+
+    fn found(router: &Router<u32>, method: HttpMethods, path: &str) -> Option<(u32, String)> {
+        match router.lookup(&method, path) {
+            RouteMatch::Found { value, pattern, .. } => Some((*value, pattern.to_string())),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_static_takes_precedence_over_param() {
+        let mut router = Router::new();
+        router.insert(HttpMethods::GET, "/user/{id}", 1).unwrap();
+        router.insert(HttpMethods::GET, "/user/me", 2).unwrap();
+
+        assert_eq!(
+            found(&router, HttpMethods::GET, "/user/me"),
+            Some((2, "/user/me".to_string()))
+        );
+        assert_eq!(
+            found(&router, HttpMethods::GET, "/user/42"),
+            Some((1, "/user/{id}".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_param_for_other_methods() {
+        let mut router = Router::new();
+        router.insert(HttpMethods::GET, "/user/me", 1).unwrap();
+        router.insert(HttpMethods::POST, "/user/{id}", 2).unwrap();
+
+        assert_eq!(
+            found(&router, HttpMethods::POST, "/user/me"),
+            Some((2, "/user/{id}".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_params_are_decoded() {
+        let mut router = Router::new();
+        router.insert(HttpMethods::GET, "/files/{name}", 1).unwrap();
+
+        match router.lookup(&HttpMethods::GET, "/files/hello%20world") {
+            RouteMatch::Found { params, .. } => {
+                assert_eq!(params.get("name").unwrap(), "hello world")
+            }
+            _ => panic!("Expected a match"),
+        }
+    }
+
+    #[test]
+    fn test_catch_all() {
+        let mut router = Router::new();
+        router
+            .insert(HttpMethods::GET, "/static/{path}*", 1)
+            .unwrap();
+        router.insert(HttpMethods::GET, "/static/index", 2).unwrap();
+
+        match router.lookup(&HttpMethods::GET, "/static/css/app.css") {
+            RouteMatch::Found { value, params, .. } => {
+                assert_eq!(*value, 1);
+                assert_eq!(params.get("path").unwrap(), "css/app.css");
+            }
+            _ => panic!("Expected a match"),
+        }
+
+        assert_eq!(
+            found(&router, HttpMethods::GET, "/static/index").unwrap().0,
+            2
+        );
+        assert_eq!(found(&router, HttpMethods::GET, "/static").unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_method_not_allowed_and_not_found() {
+        let mut router = Router::new();
+        router.insert(HttpMethods::GET, "/user", 1).unwrap();
+        router.insert(HttpMethods::PUT, "/user", 2).unwrap();
+        router.insert(HttpMethods::PATCH, "/user", 3).unwrap();
+        router.insert(HttpMethods::POST, "/user", 4).unwrap();
+
+        match router.lookup(&HttpMethods::DELETE, "/user") {
+            RouteMatch::MethodNotAllowed(allowed) => {
+                assert_eq!(
+                    allowed,
+                    vec![
+                        HttpMethods::GET,
+                        HttpMethods::POST,
+                        HttpMethods::PUT,
+                        HttpMethods::PATCH
+                    ]
+                );
+            }
+            _ => panic!("Expected method not allowed"),
+        }
+
+        assert!(matches!(
+            router.lookup(&HttpMethods::GET, "/users"),
+            RouteMatch::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_rejects_conflicting_routes() {
+        let mut router = Router::new();
+        router.insert(HttpMethods::GET, "/user/{id}", 1).unwrap();
+
+        assert_eq!(
+            router.insert(HttpMethods::GET, "/user/{name}", 2),
+            Err(RouterError::Conflict {
+                pattern: "/user/{name}".to_string(),
+                existing: "/user/{id}".to_string(),
+            })
+        );
+        assert!(router.insert(HttpMethods::GET, "/user/{id}/", 3).is_err());
+        assert!(router.insert(HttpMethods::GET, "/user/{id}", 4).is_err());
+        assert!(router.insert(HttpMethods::POST, "/user/{id}", 5).is_ok());
+    }
+
+    #[test]
+    fn test_param_names_are_per_route() {
+        let mut router = Router::new();
+        router.insert(HttpMethods::GET, "/user/{id}", 1).unwrap();
+        router
+            .insert(HttpMethods::GET, "/user/{name}/posts", 2)
+            .unwrap();
+        router.insert(HttpMethods::PUT, "/user/{key}", 3).unwrap();
+
+        match router.lookup(&HttpMethods::GET, "/user/alice/posts") {
+            RouteMatch::Found {
+                value,
+                params,
+                pattern,
+            } => {
+                assert_eq!(*value, 2);
+                assert_eq!(pattern, "/user/{name}/posts");
+                assert_eq!(params.get("name").unwrap(), "alice");
+                assert!(!params.contains_key("id"));
+            }
+            _ => panic!("Expected a match"),
+        }
+
+        match router.lookup(&HttpMethods::GET, "/user/42") {
+            RouteMatch::Found { params, .. } => assert_eq!(params.get("id").unwrap(), "42"),
+            _ => panic!("Expected a match"),
+        }
+
+        match router.lookup(&HttpMethods::PUT, "/user/42") {
+            RouteMatch::Found { params, .. } => assert_eq!(params.get("key").unwrap(), "42"),
+            _ => panic!("Expected a match"),
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_patterns() {
+        let mut router = Router::new();
+
+        assert!(router.insert(HttpMethods::GET, "/user/{id", 1).is_err());
+        assert!(router.insert(HttpMethods::GET, "/user/id-{id}", 1).is_err());
+        assert!(router.insert(HttpMethods::GET, "/files/*/edit", 1).is_err());
+        assert!(router.insert(HttpMethods::GET, "/user/{}", 1).is_err());
+        assert!(matches!(
+            router.insert(HttpMethods::GET, "/user/{id:\\d+}", 1),
+            Err(RouterError::InvalidPattern { .. })
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        context::{HttpRequest, HttpResponse},
+        extract::{FromRequest, Header, LastEventId},
+        sse::{Event, Sse},
+        types::IntoResponse,
+    };
+    use actix_web::body::MessageBody;
+    use futures_util::{future::poll_fn, stream};
+    use std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[test]
+    fn test_event_format() {
+        assert_eq!(Event::new().data("hello").to_string(), "data: hello\n\n");
+        assert_eq!(
+            Event::new().data("line 1\r\nline 2\n").to_string(),
+            "data: line 1\ndata: line 2\ndata: \n\n"
+        );
+        assert_eq!(
+            Event::new()
+                .comment("ping\revent: admin")
+                .data("hi\revent: admin\rid: 999")
+                .to_string(),
+            ": ping\n: event: admin\ndata: hi\ndata: event: admin\ndata: id: 999\n\n"
+        );
+        assert_eq!(
+            Event::new()
+                .comment("ping")
+                .event("update\n")
+                .id("1\r\n2")
+                .retry(Duration::from_millis(1500))
+                .to_string(),
+            ": ping\nevent: update\nid: 12\nretry: 1500\n\n"
+        );
+
+        let event = Event::new()
+            .json_data(serde_json::json!({ "count": 1 }))
+            .unwrap();
+        assert_eq!(event.to_string(), "data: {\"count\":1}\n\n");
+    }
+
+    #[actix_web::test]
+    async fn test_sse_response() {
+        let events = stream::iter(vec![
+            Event::new().id("1").data("first"),
+            Event::new().id("2").data("second"),
+        ]);
+        let actix_response = HttpResponse::new().ok().sse(events).to_responder();
+
+        assert_eq!(actix_response.status(), 200);
+        assert_eq!(
+            actix_response.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(
+            actix_response.headers().get("cache-control").unwrap(),
+            "no-cache"
+        );
+        let body = actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "id: 1\ndata: first\n\nid: 2\ndata: second\n\n");
+    }
+
+    #[actix_web::test]
+    async fn test_sse_keep_alive() {
+        let events = stream::pending::<Event>();
+        let actix_response = Sse::new(events)
+            .keep_alive(Duration::from_millis(10))
+            .into_response(HttpResponse::new())
+            .to_responder();
+        let mut body = actix_response.into_body();
+
+        for _ in 0..2 {
+            let chunk = poll_fn(|cx| Pin::new(&mut body).poll_next(cx))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(chunk, ": keep-alive\n\n");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_sse_disconnect() {
+        let disconnected = Arc::new(AtomicBool::new(false));
+        let flag = disconnected.clone();
+        let actix_response = Sse::new(stream::pending::<Event>())
+            .on_disconnect(move || flag.store(true, Ordering::SeqCst))
+            .into_response(HttpResponse::new())
+            .to_responder();
+        drop(actix_response);
+        assert!(disconnected.load(Ordering::SeqCst));
+
+        let disconnected = Arc::new(AtomicBool::new(false));
+        let flag = disconnected.clone();
+        let actix_response = Sse::new(stream::iter(vec![Event::new().data("done")]))
+            .on_disconnect(move || flag.store(true, Ordering::SeqCst))
+            .into_response(HttpResponse::new())
+            .to_responder();
+        actix_web::body::to_bytes(actix_response.into_body())
+            .await
+            .unwrap();
+        assert!(!disconnected.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_last_event_id() {
+        let mut req = HttpRequest::new();
+        assert_eq!(req.last_event_id(), None);
+
+        req.set_header("Last-Event-ID", "41");
+        assert_eq!(req.last_event_id(), Some("41"));
+        let Header(last_id) = Header::<LastEventId>::from_request(&req).unwrap();
+        assert_eq!(last_id.0, "41");
+    }
+}
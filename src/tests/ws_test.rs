@@ -0,0 +1,199 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app::{App, DispatchTable},
+        context::{HttpRequest, HttpResponse},
+        types::Next,
+        ws::{CloseFrame, Message, WebSocket},
+    };
+    use actix_web::FromRequest;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    };
+
+    const MASK: [u8; 4] = [1, 2, 3, 4];
+
+    /// Encodes a masked frame, as sent by clients.
+    fn client_frame(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![first_byte, 0x80 | payload.len() as u8];
+        frame.extend_from_slice(&MASK);
+        frame.extend(
+            payload
+                .iter()
+                .enumerate()
+                .map(|(index, byte)| byte ^ MASK[index % 4]),
+        );
+        frame
+    }
+
+    async fn dispatch_ws(
+        table: &DispatchTable,
+        uri: &str,
+        handshake: bool,
+        frames: Vec<u8>,
+    ) -> actix_web::HttpResponse {
+        let mut req = actix_web::test::TestRequest::get().uri(uri);
+        if handshake {
+            req = req
+                .insert_header(("Upgrade", "websocket"))
+                .insert_header(("Connection", "Upgrade"))
+                .insert_header(("Sec-WebSocket-Version", "13"))
+                .insert_header(("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="));
+        }
+        let (req, mut payload) = req.set_payload(frames).to_http_parts();
+        let payload = actix_web::web::Payload::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+        table.dispatch(req, payload).await
+    }
+
+    async fn echo(req: HttpRequest, mut socket: WebSocket) {
+        let room = req.get_params("room").unwrap().to_string();
+        while let Some(message) = socket.recv().await {
+            match message.unwrap() {
+                Message::Text(text) => socket.text(format!("{}: {}", room, text)).await.unwrap(),
+                Message::Binary(bytes) => socket.binary(bytes).await.unwrap(),
+                Message::Close(frame) => {
+                    assert_eq!(
+                        frame,
+                        Some(CloseFrame {
+                            code: 1000,
+                            reason: String::new()
+                        })
+                    );
+                    socket.close(None).await.unwrap();
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_ws_echo() {
+        let mut app = App::new();
+        app.use_middleware(
+            "",
+            |req: HttpRequest, res: HttpResponse, next: Next| async move {
+                next.run(req, res.set_header("X-Middleware", "ran")).await
+            },
+        );
+        app.ws("/chat/{room}", echo);
+        let table = app.build_dispatch_table().unwrap();
+
+        let mut frames = client_frame(0x81, b"hello");
+        // A binary message split in two fragments
+        frames.extend(client_frame(0x02, &[1, 2]));
+        frames.extend(client_frame(0x80, &[3]));
+        frames.extend(client_frame(0x88, &1000u16.to_be_bytes()));
+
+        let response = dispatch_ws(&table, "/chat/lobby", true, frames).await;
+        assert_eq!(response.status(), 101);
+        assert_eq!(
+            response.headers().get("sec-websocket-accept").unwrap(),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(response.headers().get("x-middleware").unwrap(), "ran");
+
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let mut expected = vec![0x81, 12];
+        expected.extend_from_slice(b"lobby: hello");
+        expected.extend_from_slice(&[0x82, 3, 1, 2, 3]);
+        expected.extend_from_slice(&[0x88, 0]);
+        assert_eq!(body.to_vec(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_ws_sink_close() {
+        use futures_util::SinkExt;
+
+        let mut app = App::new();
+        app.ws(
+            "/bye",
+            |_req: HttpRequest, mut socket: WebSocket| async move {
+                socket.send(Message::Text("bye".to_string())).await.unwrap();
+                // Dropping the socket alone would end the body without a close message
+                SinkExt::close(&mut socket).await.unwrap();
+            },
+        );
+        let table = app.build_dispatch_table().unwrap();
+
+        let response = dispatch_ws(&table, "/bye", true, Vec::new()).await;
+        assert_eq!(response.status(), 101);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let mut expected = vec![0x81, 3];
+        expected.extend_from_slice(b"bye");
+        expected.extend_from_slice(&[0x88, 0]);
+        assert_eq!(body.to_vec(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_ws_rejected() {
+        let upgraded = Arc::new(AtomicBool::new(false));
+        let upgraded_clone = upgraded.clone();
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let statuses_clone = statuses.clone();
+        let error_statuses = Arc::new(Mutex::new(Vec::new()));
+        let error_statuses_clone = error_statuses.clone();
+
+        let mut app = App::new();
+        app.on_response(move |_req, res| {
+            statuses_clone.lock().unwrap().push(res.get_status_code())
+        });
+        app.use_error_middleware(move |err, req, res, next| {
+            error_statuses_clone
+                .lock()
+                .unwrap()
+                .push(res.get_status_code());
+            next.run(err, req, res)
+        });
+        app.use_middleware(
+            "",
+            |req: HttpRequest, res: HttpResponse, next: Next| async move {
+                match req.get_query("token") {
+                    Ok(_) => next.run(req, res).await,
+                    Err(_) => res.unauthorized().text("Missing token"),
+                }
+            },
+        );
+        app.ws("/live", move |_req: HttpRequest, socket: WebSocket| {
+            let upgraded = upgraded_clone.clone();
+            async move {
+                upgraded.store(true, Ordering::SeqCst);
+                drop(socket);
+            }
+        });
+        let table = app.build_dispatch_table().unwrap();
+
+        let response = dispatch_ws(&table, "/live", true, Vec::new()).await;
+        assert_eq!(response.status(), 401);
+
+        let response = dispatch_ws(&table, "/live?token=abc", false, Vec::new()).await;
+        assert_eq!(response.status(), 426);
+
+        let (req, mut payload) = actix_web::test::TestRequest::get()
+            .uri("/live?token=abc")
+            .insert_header(("Upgrade", "websocket"))
+            .to_http_parts();
+        let payload = actix_web::web::Payload::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+        assert_eq!(table.dispatch(req, payload).await.status(), 400);
+
+        let response = dispatch_ws(&table, "/live?token=abc", true, Vec::new()).await;
+        assert_eq!(response.status(), 101);
+        actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert!(upgraded.load(Ordering::SeqCst));
+
+        // The response is only given the 101 status once the upgrade succeeded
+        assert_eq!(*error_statuses.lock().unwrap(), vec![200]);
+        assert_eq!(*statuses.lock().unwrap(), vec![401, 426, 400, 101]);
+    }
+}
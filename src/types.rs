@@ -1,6 +1,25 @@
-use crate::{context::HttpResponse, request::HttpRequest};
-use serde::Serialize;
-use std::{collections::HashMap, fmt::Display, future::Future, pin::Pin, sync::Arc};
+use crate::{
+    app::box_future, context::HttpResponse, extract::FromRequest, request::HttpRequest,
+    ws::WebSocket,
+};
+use futures_util::{future::LocalBoxFuture, Stream};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Display,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+};
+
+pub use crate::body_stream::BodyStream;
+pub use crate::headers::{HeaderError, Headers};
+pub use crate::media_type::MediaType;
+pub use crate::multipart::{
+    Multipart, MultipartConfig, MultipartField, MultipartStream, UploadedFile,
+};
+pub use actix_web::web::Bytes;
 
 // HttpRequest types
 
@@ -9,6 +28,8 @@ pub enum RequestBodyType {
     JSON,
     TEXT,
     FORM,
+    BINARY,
+    MULTIPART,
 }
 
 impl Copy for RequestBodyType {}
@@ -18,6 +39,8 @@ pub enum RequestBodyContent {
     TEXT(String),
     JSON(serde_json::Value),
     FORM(String),
+    BINARY(Bytes),
+    MULTIPART(Multipart),
 }
 
 #[derive(Debug, PartialEq)]
@@ -28,6 +51,8 @@ pub enum HttpRequestError {
     MissingQuery(String),
 }
 
+impl std::error::Error for HttpRequestError {}
+
 impl std::fmt::Display for HttpRequestError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -39,6 +64,165 @@ impl std::fmt::Display for HttpRequestError {
     }
 }
 
+// Error types
+
+/// An error raised while handling a request.
+///
+/// Errors are passed to the error middlewares added with
+/// `App::use_error_middleware`, which turn them into the final response. If
+/// there are none, the response gets the error's status code and message.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::types::RipressError;
+///
+/// let err = RipressError::Status(404, String::from("User not found"));
+/// assert_eq!(err.status_code(), 404);
+/// assert_eq!(err.to_string(), "User not found");
+/// ```
+#[derive(Debug)]
+pub enum RipressError {
+    /// The request body could not be read or parsed.
+    InvalidBody(String),
+    /// The request body is larger than the limit, in bytes.
+    PayloadTooLarge(usize),
+    /// A value was missing from the request.
+    Request(HttpRequestError),
+    /// A handler argument could not be extracted from the request.
+    Extract(String),
+    /// An error with a specific status code and message.
+    Status(i32, String),
+    /// Any other error, e.g. one returned by application code.
+    Other(Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+impl RipressError {
+    /// Wraps an application error.
+    pub fn other<E>(err: E) -> Self
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        RipressError::Other(err.into())
+    }
+
+    /// Returns the status code of the response for this error.
+    pub fn status_code(&self) -> i32 {
+        match self {
+            RipressError::InvalidBody(_) => 400,
+            RipressError::PayloadTooLarge(_) => 413,
+            RipressError::Request(_) => 400,
+            RipressError::Extract(_) => 400,
+            RipressError::Status(code, _) => *code,
+            RipressError::Other(_) => 500,
+        }
+    }
+
+    /// Returns the wrapped application error if it is of type `E`.
+    pub fn downcast_ref<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        match self {
+            RipressError::Other(err) => err.downcast_ref::<E>(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for RipressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RipressError::InvalidBody(message) => write!(f, "{}", message),
+            RipressError::PayloadTooLarge(limit) => {
+                write!(f, "Request body is larger than {} bytes", limit)
+            }
+            RipressError::Request(err) => write!(f, "{}", err),
+            RipressError::Extract(message) => write!(f, "{}", message),
+            RipressError::Status(_, message) => write!(f, "{}", message),
+            RipressError::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for RipressError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RipressError::Other(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<HttpRequestError> for RipressError {
+    fn from(err: HttpRequestError) -> Self {
+        RipressError::Request(err)
+    }
+}
+
+impl From<QueryError> for RipressError {
+    fn from(err: QueryError) -> Self {
+        RipressError::Extract(err.to_string())
+    }
+}
+
+/// An error deserializing the query string with `req.query`.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::context::HttpRequest;
+/// use serde::Deserialize;
+///
+/// ##[derive(Debug, Deserialize)]
+/// struct Pagination {
+///     page: u32,
+/// }
+///
+/// let req = HttpRequest::new();
+/// let err = req.query::<Pagination>().unwrap_err();
+/// assert_eq!(err.field(), Some("page"));
+/// assert_eq!(err.to_string(), "Invalid query parameter page: missing field");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    field: Option<String>,
+    message: String,
+}
+
+impl QueryError {
+    pub(crate) fn new(field: Option<String>, message: String) -> Self {
+        QueryError { field, message }
+    }
+
+    /// Prefixes the failing field with the key it is nested in, e.g. `filter[status]`.
+    pub(crate) fn with_field(mut self, field: &str) -> Self {
+        self.field = Some(match self.field {
+            Some(nested) => format!("{}[{}]", field, nested),
+            None => field.to_string(),
+        });
+        self
+    }
+
+    /// Returns the name of the query parameter that failed, if known.
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+
+    /// Returns the reason the query parameter failed.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "Invalid query parameter {}: {}", field, self.message),
+            None => write!(f, "Invalid query: {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
 // HttpResponse types
 
 #[derive(PartialEq, Debug, Clone)]
@@ -46,13 +230,100 @@ pub enum ResponseContentType {
     JSON,
     TEXT,
     HTML,
+    XML,
+    CSS,
+    JAVASCRIPT,
+    /// `application/octet-stream`, for binary data.
+    BINARY,
+    /// Any other media type, e.g. `application/pdf`.
+    CUSTOM(String),
 }
 
-#[derive(Serialize, PartialEq, Debug)]
+impl ResponseContentType {
+    /// Returns the media type sent in the `Content-Type` header.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ripress::types::ResponseContentType;
+    ///
+    /// assert_eq!(ResponseContentType::CSS.as_str(), "text/css");
+    /// assert_eq!(ResponseContentType::from("application/pdf").as_str(), "application/pdf");
+    /// ```
+    pub fn as_str(&self) -> &str {
+        match self {
+            ResponseContentType::JSON => "application/json",
+            ResponseContentType::TEXT => "text/plain",
+            ResponseContentType::HTML => "text/html",
+            ResponseContentType::XML => "application/xml",
+            ResponseContentType::CSS => "text/css",
+            ResponseContentType::JAVASCRIPT => "text/javascript",
+            ResponseContentType::BINARY => "application/octet-stream",
+            ResponseContentType::CUSTOM(media_type) => media_type,
+        }
+    }
+}
+
+/// Uses the built-in variant for its media type, `CUSTOM` otherwise.
+impl From<&str> for ResponseContentType {
+    fn from(media_type: &str) -> Self {
+        let media_type = media_type.trim();
+        match media_type.to_ascii_lowercase().as_str() {
+            "application/json" => ResponseContentType::JSON,
+            "text/plain" => ResponseContentType::TEXT,
+            "text/html" => ResponseContentType::HTML,
+            "application/xml" => ResponseContentType::XML,
+            "text/css" => ResponseContentType::CSS,
+            "text/javascript" => ResponseContentType::JAVASCRIPT,
+            "application/octet-stream" => ResponseContentType::BINARY,
+            _ => ResponseContentType::CUSTOM(media_type.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ResponseContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The error of a chunk of a `ResponseStream`.
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A response body sent in chunks as it is produced.
+pub(crate) struct ResponseStream(
+    pub(crate) Pin<Box<dyn Stream<Item = Result<Bytes, BoxError>> + Send>>,
+);
+
+impl std::fmt::Debug for ResponseStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ResponseStream").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for ResponseStream {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// A file sent as the response body, opened when the response is sent.
+#[derive(PartialEq, Debug)]
+pub(crate) struct ResponseFile {
+    pub(crate) path: PathBuf,
+    /// The file name of a download, sent as an attachment.
+    pub(crate) download_name: Option<String>,
+}
+
+#[derive(PartialEq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub(crate) enum ResponseContentBody {
     JSON(serde_json::Value),
     TEXT(String),
     HTML(String),
+    BINARY(Bytes),
+    STREAM(ResponseStream),
+    FILE(ResponseFile),
 }
 
 impl ResponseContentBody {
@@ -80,6 +351,27 @@ impl std::fmt::Display for HttpResponseError {
 
 // App types
 
+#[derive(Debug, PartialEq)]
+pub enum RouterError {
+    InvalidPattern { pattern: String, reason: String },
+    Conflict { pattern: String, existing: String },
+}
+
+impl std::fmt::Display for RouterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RouterError::InvalidPattern { pattern, reason } => {
+                write!(f, "Invalid route pattern {}: {}", pattern, reason)
+            }
+            RouterError::Conflict { pattern, existing } => {
+                write!(f, "Route {} conflicts with route {}", pattern, existing)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RouterError {}
+
 #[derive(Eq, Hash, PartialEq, Clone, Debug)]
 pub enum HttpMethods {
     GET,
@@ -104,7 +396,364 @@ impl Display for HttpMethods {
 
 pub type Fut = Pin<Box<dyn Future<Output = HttpResponse> + Send + 'static>>;
 pub type Handler = Arc<dyn Fn(HttpRequest, HttpResponse) -> Fut + Send + Sync + 'static>;
-pub(crate) type Routes = HashMap<&'static str, HashMap<HttpMethods, Handler>>;
+pub(crate) type WsHandler =
+    Arc<dyn Fn(HttpRequest, WebSocket) -> LocalBoxFuture<'static, ()> + Send + Sync + 'static>;
+pub(crate) type Routes = HashMap<&'static str, HashMap<HttpMethods, Endpoint>>;
+pub type ResponseHook = Arc<dyn Fn(&HttpRequest, &HttpResponse) + Send + Sync + 'static>;
+pub type HookError = Box<dyn std::error::Error + Send + Sync + 'static>;
+pub type LifecycleFut = Pin<Box<dyn Future<Output = Result<(), HookError>> + Send + 'static>>;
+pub type LifecycleHook = Arc<dyn Fn() -> LifecycleFut + Send + Sync + 'static>;
+pub(crate) type StateMap = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+/// A route handler along with the settings of its route.
+#[derive(Clone)]
+pub(crate) struct Endpoint {
+    pub(crate) handler: Handler,
+    pub(crate) body_limit: Option<usize>,
+    pub(crate) stream_body: bool,
+    /// The socket handler of a WebSocket route, called after the upgrade.
+    pub(crate) ws: Option<WsHandler>,
+}
+
+/// A value that can be returned from a handler.
+///
+/// The value is applied to the response passed down by the middlewares, so
+/// headers and cookies they set are kept. Handlers returning one are added
+/// to a route with `handler`.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, context::HttpRequest, types::{handler, Html, RipressError}};
+///
+/// async fn hello() -> &'static str {
+///     "Hello, World!"
+/// }
+///
+/// async fn created() -> (u16, Html<&'static str>) {
+///     (201, Html("<h1>Created</h1>"))
+/// }
+///
+/// async fn user(req: HttpRequest) -> Result<String, RipressError> {
+///     let id = req.get_params("id")?;
+///     Ok(format!("User {}", id))
+/// }
+///
+/// let mut app = App::new();
+/// app.get("/", handler(hello));
+/// app.post("/", handler(created));
+/// app.get("/user/{id}", handler(user));
+/// ```
+pub trait IntoResponse {
+    /// Applies the value to the response.
+    fn into_response(self, res: HttpResponse) -> HttpResponse;
+}
+
+impl IntoResponse for HttpResponse {
+    fn into_response(self, _res: HttpResponse) -> HttpResponse {
+        self
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        res.text(self)
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        res.text(self)
+    }
+}
+
+impl IntoResponse for Bytes {
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        res.bytes(self)
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        res.bytes(self)
+    }
+}
+
+impl IntoResponse for serde_json::Value {
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        res.json(self)
+    }
+}
+
+/// Sets the status code after applying the value, e.g. `(201, "Created")`.
+impl<T: IntoResponse> IntoResponse for (u16, T) {
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        let (status, value) = self;
+        value.into_response(res).status(status as i32)
+    }
+}
+
+/// Passes errors to the error middlewares, like `res.error` does.
+impl<T, E> IntoResponse for Result<T, E>
+where
+    T: IntoResponse,
+    E: Into<RipressError>,
+{
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        match self {
+            Ok(value) => value.into_response(res),
+            Err(err) => res.error(err),
+        }
+    }
+}
+
+/// An HTML response body.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::types::Html;
+///
+/// async fn index() -> Html<String> {
+///     Html(format!("<h1>{}</h1>", "Hello"))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Html<T>(pub T);
+
+impl<T: Into<String>> IntoResponse for Html<T> {
+    fn into_response(self, res: HttpResponse) -> HttpResponse {
+        res.html(self.0)
+    }
+}
+
+/// Marks handlers that don't take the `HttpResponse` as their last argument.
+#[doc(hidden)]
+pub struct WithoutResponse<T>(std::marker::PhantomData<T>);
+
+/// A function that can be used as a route handler.
+///
+/// Implemented for functions taking up to 8 arguments implementing
+/// `FromRequest`, optionally followed by the `HttpResponse`, and returning a
+/// future of a value implementing `IntoResponse`. This includes the classic
+/// `(HttpRequest, HttpResponse)` handlers. Wrap them with `handler` to add
+/// them as a route.
+///
+/// Handlers without the `HttpResponse` argument get their return value applied
+/// to the response passed down by the middlewares. Handlers taking it and
+/// returning something other than an `HttpResponse` have the value applied to
+/// a copy of its status, headers and cookies.
+///
+/// If an argument cannot be extracted, the handler isn't called and the error
+/// is passed to the error middlewares, answering with 400 Bad Request by default.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, context::HttpResponse, extract::{Json, Path}, types::handler};
+/// use serde::Deserialize;
+///
+/// ##[derive(Deserialize)]
+/// struct UserPath {
+///     id: u32,
+/// }
+///
+/// ##[derive(Deserialize)]
+/// struct Rename {
+///     name: String,
+/// }
+///
+/// async fn rename(Path(path): Path<UserPath>, Json(body): Json<Rename>, res: HttpResponse) -> HttpResponse {
+///     res.ok().text(format!("User {} is now {}", path.id, body.name))
+/// }
+///
+/// async fn user(Path(path): Path<UserPath>) -> String {
+///     format!("User {}", path.id)
+/// }
+///
+/// let mut app = App::new();
+/// app.put("/user/{id}", handler(rename));
+/// app.get("/user/{id}", handler(user));
+/// ```
+pub trait IntoHandler<Args>: Send + Sync + 'static {
+    fn into_handler(self) -> Handler;
+}
+
+macro_rules! impl_into_handler {
+    ($($ty:ident $var:ident),*) => {
+        impl<F, Fut, $($ty,)*> IntoHandler<($($ty,)* HttpResponse,)> for F
+        where
+            F: Fn($($ty,)* HttpResponse) -> Fut + Send + Sync + 'static,
+            Fut: Future + Send + 'static,
+            Fut::Output: IntoResponse,
+            $($ty: FromRequest,)*
+        {
+            #[allow(unused_variables)]
+            fn into_handler(self) -> Handler {
+                Arc::new(move |req: HttpRequest, res: HttpResponse| {
+                    $(
+                        let $var = match $ty::from_request(&req) {
+                            Ok(value) => value,
+                            Err(err) => return box_future(async move { res.error(err) }),
+                        };
+                    )*
+                    let head = res.head();
+                    let fut = self($($var,)* res);
+                    box_future(async move { fut.await.into_response(head) })
+                })
+            }
+        }
+
+        impl<F, Fut, $($ty,)*> IntoHandler<WithoutResponse<($($ty,)*)>> for F
+        where
+            F: Fn($($ty,)*) -> Fut + Send + Sync + 'static,
+            Fut: Future + Send + 'static,
+            Fut::Output: IntoResponse,
+            $($ty: FromRequest,)*
+        {
+            #[allow(unused_variables)]
+            fn into_handler(self) -> Handler {
+                Arc::new(move |req: HttpRequest, res: HttpResponse| {
+                    $(
+                        let $var = match $ty::from_request(&req) {
+                            Ok(value) => value,
+                            Err(err) => return box_future(async move { res.error(err) }),
+                        };
+                    )*
+                    let fut = self($($var,)*);
+                    box_future(async move { fut.await.into_response(res) })
+                })
+            }
+        }
+    };
+}
+
+impl_into_handler!();
+impl_into_handler!(T1 t1);
+impl_into_handler!(T1 t1, T2 t2);
+impl_into_handler!(T1 t1, T2 t2, T3 t3);
+impl_into_handler!(T1 t1, T2 t2, T3 t3, T4 t4);
+impl_into_handler!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5);
+impl_into_handler!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6);
+impl_into_handler!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7);
+impl_into_handler!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8);
+
+/// Turns an `IntoHandler` function into a handler the route methods accept.
+///
+/// The route methods only take `(HttpRequest, HttpResponse)` handlers
+/// directly, on purpose: a route method generic over `IntoHandler` can't infer
+/// the argument types of closures. Wrap handlers taking extractor arguments,
+/// or returning something other than an `HttpResponse`, with `handler`.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, extract::Path, types::handler};
+/// use serde::Deserialize;
+///
+/// ##[derive(Deserialize)]
+/// struct UserPath {
+///     id: u32,
+/// }
+///
+/// async fn user(Path(path): Path<UserPath>) -> String {
+///     format!("User {}", path.id)
+/// }
+///
+/// let mut app = App::new();
+/// app.get("/user/{id}", handler(user));
+/// app.get("/", |_req, res| async move { res.ok() });
+/// ```
+pub fn handler<F, Args>(
+    handler: F,
+) -> impl Fn(HttpRequest, HttpResponse) -> Fut + Send + Sync + 'static
+where
+    F: IntoHandler<Args>,
+{
+    let handler = handler.into_handler();
+    move |req, res| handler(req, res)
+}
+
+/// The paths a middleware applies to.
+///
+/// A pattern matches a request path on whole segments, so `/auth` matches
+/// `/auth` and `/auth/login` but not `/authors`. A `*` segment matches any
+/// single segment and a `**` segment matches any number of segments, e.g.
+/// `/api/*/admin` matches `/api/v1/admin/users`. Paths matching one of the
+/// excluded patterns are skipped.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::types::MiddlewarePath;
+///
+/// let path = MiddlewarePath::new("/api").exclude("/api/login");
+/// assert!(path.matches("/api/users"));
+/// assert!(!path.matches("/api/login"));
+/// assert!(!path.matches("/apis"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiddlewarePath {
+    include: String,
+    exclude: Vec<String>,
+}
+
+impl MiddlewarePath {
+    /// Creates a path matching the pattern and everything below it.
+    pub fn new(pattern: &str) -> Self {
+        MiddlewarePath {
+            include: pattern.to_string(),
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Excludes the paths matching the pattern and everything below it.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.to_string());
+        self
+    }
+
+    /// Returns `true` if a middleware mounted on this path applies to the request path.
+    pub fn matches(&self, path: &str) -> bool {
+        matches_prefix(&self.include, path)
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| matches_prefix(pattern, path))
+    }
+}
+
+impl From<&str> for MiddlewarePath {
+    fn from(pattern: &str) -> Self {
+        MiddlewarePath::new(pattern)
+    }
+}
+
+impl From<Option<&str>> for MiddlewarePath {
+    fn from(pattern: Option<&str>) -> Self {
+        MiddlewarePath::new(pattern.unwrap_or("/"))
+    }
+}
+
+/// Checks if the pattern matches the leading segments of the path.
+fn matches_prefix(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    matches_segments(&pattern, &path)
+}
+
+fn matches_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => true,
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((first, path_rest)) if *segment == "*" || segment == first => {
+                matches_segments(rest, path_rest)
+            }
+            _ => false,
+        },
+    }
+}
 
 pub trait Middleware: Send + Sync + 'static {
     fn handle(
@@ -118,6 +767,62 @@ pub trait Middleware: Send + Sync + 'static {
     fn clone_box(&self) -> Box<dyn Middleware>;
 }
 
+/// A middleware written with a plain `async fn`, registered with `App::use_layer`.
+///
+/// Every `AsyncMiddleware` that is `Clone` is also a `Middleware`, so there is
+/// no need to box futures or implement `clone_box` by hand. The middleware is
+/// cloned for every request, so state shared between requests (counters,
+/// caches, connections) should be kept behind an `Arc`.
+///
+/// ## Example
+///
+/// ```
+/// use ripress::{app::App, context::{HttpRequest, HttpResponse}, types::{AsyncMiddleware, Next}};
+/// use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+///
+/// ##[derive(Clone, Default)]
+/// struct RequestCounter {
+///     count: Arc<AtomicUsize>,
+/// }
+///
+/// impl AsyncMiddleware for RequestCounter {
+///     async fn handle(&self, req: HttpRequest, res: HttpResponse, next: Next) -> HttpResponse {
+///         self.count.fetch_add(1, Ordering::SeqCst);
+///         next.run(req, res).await
+///     }
+/// }
+///
+/// let mut app = App::new();
+/// app.use_layer(RequestCounter::default());
+/// ```
+pub trait AsyncMiddleware: Send + Sync + 'static {
+    fn handle(
+        &self,
+        req: HttpRequest,
+        res: HttpResponse,
+        next: Next,
+    ) -> impl Future<Output = HttpResponse> + Send;
+}
+
+impl<T> Middleware for T
+where
+    T: AsyncMiddleware + Clone,
+{
+    fn handle(
+        &self,
+        req: HttpRequest,
+        res: HttpResponse,
+        next: Next,
+    ) -> Pin<Box<dyn Future<Output = HttpResponse> + Send + 'static>> {
+        let middleware = self.clone();
+        Box::pin(async move { AsyncMiddleware::handle(&middleware, req, res, next).await })
+    }
+
+    fn clone_box(&self) -> Box<dyn Middleware> {
+        Box::new(self.clone())
+    }
+}
+
 // Implement Clone for Box<dyn Middleware>
 impl Clone for Box<dyn Middleware> {
     fn clone(&self) -> Self {
@@ -125,6 +830,17 @@ impl Clone for Box<dyn Middleware> {
     }
 }
 
+// Allow boxed middlewares of different types to be passed where a Middleware is expected
+impl Middleware for Box<dyn Middleware> {
+    fn handle(&self, req: HttpRequest, res: HttpResponse, next: Next) -> Fut {
+        (**self).handle(req, res, next)
+    }
+
+    fn clone_box(&self) -> Box<dyn Middleware> {
+        (**self).clone_box()
+    }
+}
+
 pub struct Next {
     pub middleware: Vec<Box<dyn Middleware>>,
     pub handler: Handler,
@@ -151,3 +867,52 @@ impl Next {
         }
     }
 }
+
+pub trait ErrorMiddleware: Send + Sync + 'static {
+    fn handle(
+        &self,
+        err: RipressError,
+        req: HttpRequest,
+        res: HttpResponse,
+        next: ErrorNext,
+    ) -> Pin<Box<dyn Future<Output = HttpResponse> + Send + 'static>>;
+
+    fn clone_box(&self) -> Box<dyn ErrorMiddleware>;
+}
+
+impl Clone for Box<dyn ErrorMiddleware> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+pub struct ErrorNext {
+    pub middleware: Vec<Box<dyn ErrorMiddleware>>,
+}
+
+impl Default for ErrorNext {
+    fn default() -> Self {
+        ErrorNext::new()
+    }
+}
+
+impl ErrorNext {
+    pub fn new() -> Self {
+        ErrorNext {
+            middleware: Vec::new(),
+        }
+    }
+
+    pub async fn run(self, err: RipressError, req: HttpRequest, res: HttpResponse) -> HttpResponse {
+        if let Some((current, rest)) = self.middleware.split_first() {
+            // Call the next error middleware
+            let next = ErrorNext {
+                middleware: rest.to_vec(),
+            };
+            current.handle(err, req, res, next).await
+        } else {
+            // No more error middleware, respond with the error itself
+            res.status(err.status_code()).text(err.to_string())
+        }
+    }
+}
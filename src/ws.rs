@@ -0,0 +1,316 @@
+//! WebSocket routes.
+//!
+//! Add a route with `App::ws`, its handler is called with the upgrade request
+//! and a `WebSocket` once the connection is upgraded. Global middlewares run
+//! before the upgrade, so they can authenticate the request or reject it with
+//! a regular response.
+
+use crate::types::Bytes;
+use actix_web::web::BytesMut;
+use actix_ws::{CloseCode, CloseReason, Item};
+use futures_util::{
+    future::LocalBoxFuture,
+    ready,
+    sink::Sink,
+    stream::{Stream, StreamExt},
+};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A message received from or sent to a `WebSocket`.
+///
+/// Fragmented messages are received whole.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Bytes),
+    Ping(Bytes),
+    Pong(Bytes),
+    /// The connection is closing, with the close code and reason if any.
+    Close(Option<CloseFrame>),
+}
+
+/// The code and reason of a close message, e.g. `1000` for a normal closure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+impl From<CloseReason> for CloseFrame {
+    fn from(reason: CloseReason) -> Self {
+        CloseFrame {
+            code: reason.code.into(),
+            reason: reason.description.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<CloseFrame> for CloseReason {
+    fn from(frame: CloseFrame) -> Self {
+        CloseReason {
+            code: CloseCode::from(frame.code),
+            description: (!frame.reason.is_empty()).then_some(frame.reason),
+        }
+    }
+}
+
+/// An error reading from or writing to a `WebSocket`.
+#[derive(Debug)]
+pub enum WebSocketError {
+    /// The client sent an invalid frame.
+    Protocol(String),
+    /// A message is larger than the limit of the route, in bytes.
+    MessageTooLarge(usize),
+    /// The connection is closed.
+    Closed,
+}
+
+impl std::fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WebSocketError::Protocol(err) => write!(f, "WebSocket protocol error: {}", err),
+            WebSocketError::MessageTooLarge(limit) => {
+                write!(f, "WebSocket message is larger than {} bytes", limit)
+            }
+            WebSocketError::Closed => write!(f, "WebSocket is closed"),
+        }
+    }
+}
+
+impl std::error::Error for WebSocketError {}
+
+/// An upgraded WebSocket connection.
+///
+/// Messages are received as a `Stream` and sent as a `Sink`, use `StreamExt`
+/// and `SinkExt` (or `split` them) to handle both. Pings aren't answered
+/// automatically, reply with a `Message::Pong`. Messages are limited to the
+/// body limit of the route.
+///
+/// ## Example
+///
+/// ```no_run
+/// use futures_util::{SinkExt, StreamExt};
+/// use ripress::{app::App, context::HttpRequest, ws::{Message, WebSocket}};
+///
+/// async fn echo(req: HttpRequest, mut socket: WebSocket) {
+///     let room = req.get_params("room").unwrap_or("lobby").to_string();
+///     while let Some(Ok(message)) = socket.next().await {
+///         let reply = match message {
+///             Message::Text(text) => Message::Text(format!("[{}] {}", room, text)),
+///             Message::Ping(bytes) => Message::Pong(bytes),
+///             Message::Close(_) => break,
+///             _ => continue,
+///         };
+///         if socket.send(reply).await.is_err() {
+///             break;
+///         }
+///     }
+///     let _ = socket.close(None).await;
+/// }
+///
+/// let mut app = App::new();
+/// app.ws("/chat/{room}", echo);
+/// ```
+pub struct WebSocket {
+    session: actix_ws::Session,
+    messages: actix_ws::MessageStream,
+    max_size: usize,
+    /// A fragmented message being received, and whether it is text.
+    fragments: Option<(bool, BytesMut)>,
+    sending: Option<LocalBoxFuture<'static, Result<(), actix_ws::Closed>>>,
+    /// Whether a close message was sent.
+    closed: bool,
+}
+
+impl std::fmt::Debug for WebSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("WebSocket").finish_non_exhaustive()
+    }
+}
+
+impl WebSocket {
+    pub(crate) fn new(
+        session: actix_ws::Session,
+        messages: actix_ws::MessageStream,
+        max_size: usize,
+    ) -> Self {
+        WebSocket {
+            session,
+            messages: messages.max_frame_size(max_size),
+            max_size,
+            fragments: None,
+            sending: None,
+            closed: false,
+        }
+    }
+
+    /// Receives the next message, `None` once the connection is closed.
+    pub async fn recv(&mut self) -> Option<Result<Message, WebSocketError>> {
+        self.next().await
+    }
+
+    /// Sends a text message.
+    pub async fn text(&mut self, text: impl Into<String>) -> Result<(), WebSocketError> {
+        self.session
+            .text(text.into())
+            .await
+            .map_err(|_| WebSocketError::Closed)
+    }
+
+    /// Sends a binary message.
+    pub async fn binary(&mut self, bytes: impl Into<Bytes>) -> Result<(), WebSocketError> {
+        self.session
+            .binary(bytes.into())
+            .await
+            .map_err(|_| WebSocketError::Closed)
+    }
+
+    /// Sends a close message and closes the connection.
+    pub async fn close(&mut self, frame: Option<CloseFrame>) -> Result<(), WebSocketError> {
+        self.closed = true;
+        self.session
+            .clone()
+            .close(frame.map(Into::into))
+            .await
+            .map_err(|_| WebSocketError::Closed)
+    }
+
+    /// Appends a fragment to the message being received, returning the message on the last one.
+    fn push_fragment(&mut self, item: Item) -> Result<Option<Message>, WebSocketError> {
+        let (bytes, last) = match item {
+            Item::FirstText(_) | Item::FirstBinary(_) if self.fragments.is_some() => {
+                return Err(WebSocketError::Protocol(
+                    "Fragmented message started twice".to_string(),
+                ));
+            }
+            Item::FirstText(bytes) => {
+                self.fragments = Some((true, BytesMut::new()));
+                (bytes, false)
+            }
+            Item::FirstBinary(bytes) => {
+                self.fragments = Some((false, BytesMut::new()));
+                (bytes, false)
+            }
+            Item::Continue(bytes) => (bytes, false),
+            Item::Last(bytes) => (bytes, true),
+        };
+
+        let (_, buffer) = self.fragments.as_mut().ok_or_else(|| {
+            WebSocketError::Protocol("Continuation without a first fragment".to_string())
+        })?;
+        if buffer.len() + bytes.len() > self.max_size {
+            self.fragments = None;
+            return Err(WebSocketError::MessageTooLarge(self.max_size));
+        }
+        buffer.extend_from_slice(&bytes);
+        if !last {
+            return Ok(None);
+        }
+
+        match self.fragments.take() {
+            Some((true, buffer)) => String::from_utf8(buffer.to_vec())
+                .map(|text| Some(Message::Text(text)))
+                .map_err(|_| {
+                    WebSocketError::Protocol("Text message isn't valid UTF-8".to_string())
+                }),
+            Some((false, buffer)) => Ok(Some(Message::Binary(buffer.freeze()))),
+            None => Ok(None),
+        }
+    }
+
+    fn poll_sending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), WebSocketError>> {
+        if let Some(sending) = &mut self.sending {
+            let result = ready!(sending.as_mut().poll(cx));
+            self.sending = None;
+            result.map_err(|_| WebSocketError::Closed)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for WebSocket {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match ready!(self.messages.poll_next_unpin(cx)) {
+                None => return Poll::Ready(None),
+                Some(Err(err)) => {
+                    return Poll::Ready(Some(Err(WebSocketError::Protocol(err.to_string()))))
+                }
+                Some(Ok(message)) => message,
+            };
+
+            let message = match message {
+                actix_ws::Message::Text(text) => Message::Text(text.to_string()),
+                actix_ws::Message::Binary(bytes) => Message::Binary(bytes),
+                actix_ws::Message::Ping(bytes) => Message::Ping(bytes),
+                actix_ws::Message::Pong(bytes) => Message::Pong(bytes),
+                actix_ws::Message::Close(reason) => Message::Close(reason.map(Into::into)),
+                actix_ws::Message::Continuation(item) => match self.push_fragment(item) {
+                    Ok(Some(message)) => message,
+                    Ok(None) => continue,
+                    Err(err) => return Poll::Ready(Some(Err(err))),
+                },
+                actix_ws::Message::Nop => continue,
+            };
+            return Poll::Ready(Some(Ok(message)));
+        }
+    }
+}
+
+impl Sink<Message> for WebSocket {
+    type Error = WebSocketError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_sending(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+        if matches!(message, Message::Close(_)) {
+            self.closed = true;
+        }
+        let mut session = self.session.clone();
+        self.sending = Some(Box::pin(async move {
+            match message {
+                Message::Text(text) => session.text(text).await,
+                Message::Binary(bytes) => session.binary(bytes).await,
+                Message::Ping(bytes) => session.ping(&bytes).await,
+                Message::Pong(bytes) => session.pong(&bytes).await,
+                Message::Close(frame) => session.close(frame.map(Into::into)).await,
+            }
+        }));
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_sending(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.poll_sending(cx))?;
+        if !self.closed {
+            // Sends a close message without a reason, like `close(None)`
+            self.closed = true;
+            let session = self.session.clone();
+            self.sending = Some(Box::pin(session.close(None)));
+            ready!(self.poll_sending(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Returns `true` if the request asks to be upgraded to a WebSocket.
+pub(crate) fn is_handshake(req: &actix_web::HttpRequest) -> bool {
+    req.headers()
+        .get(actix_web::http::header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|protocol| protocol.trim().eq_ignore_ascii_case("websocket"))
+        })
+}